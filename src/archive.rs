@@ -0,0 +1,349 @@
+
+use std::collections::BTreeMap;
+use std::path::{Path, Component};
+use std::fs;
+use serde::{Serialize, Deserialize};
+use ring::digest;
+use chrono::Utc;
+
+use crate::{
+	PlayerId,
+	RoomId,
+	Timestamp,
+	savestate::SaveState,
+	playerstate::PlayerState,
+	persistence::{PersistentStorage, LoaderError},
+	auth::{self, UserRegistry, User},
+	worldloader::{WorldLoader, WorldMeta},
+	purgatory,
+	Result,
+	aerr,
+	util::write_file_safe
+};
+
+#[allow(non_upper_case_globals)]
+const archive_format: u32 = 1;
+
+/// The directories in the content that only hold content files, so stale files in them can be removed
+const CONTENT_DIRECTORIES: [&str; 3] = ["encyclopediae", "maps", "stamps"];
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Manifest {
+	pub format: u32,
+	pub server_version: String,
+	pub created: String,
+	pub content_hash: String,
+	pub world: WorldMeta,
+	pub rooms: usize,
+	pub players: usize,
+	pub users: usize
+}
+
+/// Everything that is needed to run a world: the content, the savegames and the user registrations
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorldArchive {
+	pub manifest: Manifest,
+	content: BTreeMap<String, String>,
+	steps: Option<i64>,
+	rooms: Vec<(RoomId, SaveState)>,
+	players: Vec<(PlayerId, PlayerState)>,
+	users: Vec<(PlayerId, User)>
+}
+
+impl WorldArchive {
+
+	pub fn export(loader: &WorldLoader, storage: &dyn PersistentStorage, registry: &dyn UserRegistry) -> Result<Self> {
+		let content = read_content(&loader.directory)?;
+		let steps = match storage.load_world_meta() {
+			Ok(time) => Some(time.0),
+			Err(LoaderError::MissingResource(_)) => None,
+			Err(LoaderError::InvalidResource(err)) => return Err(aerr!("failed to load world meta: {}", err))
+		};
+		let mut rooms = Vec::new();
+		for id in storage.list_rooms()? {
+			match storage.load_room(id.clone()) {
+				Ok(state) => rooms.push((id, state)),
+				Err(LoaderError::MissingResource(err)) | Err(LoaderError::InvalidResource(err)) =>
+					return Err(aerr!("failed to load room {}: {}", id, err))
+			}
+		}
+		let mut players = Vec::new();
+		for id in storage.list_players()? {
+			match storage.load_player(id.clone()) {
+				Ok(state) => players.push((id, state)),
+				Err(LoaderError::MissingResource(err)) | Err(LoaderError::InvalidResource(err)) =>
+					return Err(aerr!("failed to load player {}: {}", id, err))
+			}
+		}
+		let mut users = Vec::new();
+		for id in registry.list_users()? {
+			match registry.load_user(&id) {
+				Ok(user) => users.push((id, user)),
+				Err(auth::LoaderError::MissingResource(err)) | Err(auth::LoaderError::InvalidResource(err)) =>
+					return Err(aerr!("failed to load user {}: {}", id, err))
+			}
+		}
+		Ok(Self {
+			manifest: Manifest {
+				format: archive_format,
+				server_version: env!("CARGO_PKG_VERSION").to_string(),
+				created: Utc::now().to_rfc3339(),
+				content_hash: content_hash(&content),
+				world: loader.load_world_meta()?,
+				rooms: rooms.len(),
+				players: players.len(),
+				users: users.len()
+			},
+			content,
+			steps,
+			rooms,
+			players,
+			users
+		})
+	}
+
+	pub fn write(&self, path: &Path) -> Result<()> {
+		write_file_safe(path, serde_json::to_string(self)?)
+	}
+
+	pub fn read(path: &Path) -> Result<Self> {
+		let archive: Self = serde_json::from_str(&fs::read_to_string(path)?)?;
+		if archive.manifest.format != archive_format {
+			return Err(aerr!("unsupported archive format {}", archive.manifest.format));
+		}
+		if content_hash(&archive.content) != archive.manifest.content_hash {
+			return Err(aerr!("archive content does not match the manifest"));
+		}
+		Ok(archive)
+	}
+
+	/// Replace the content in the directory by the content of the archive.
+	/// Files in the content directories that are not in the archive are removed, so they can't make the content differ from the archive.
+	/// Symbolic links and other files are left alone.
+	pub fn write_content(&self, directory: &Path) -> Result<()> {
+		for (name, text) in self.content.iter() {
+			let relative = Path::new(name);
+			if !relative.components().all(|c| matches!(c, Component::Normal(_))) {
+				return Err(aerr!("invalid content path in archive: {}", name));
+			}
+			let path = directory.join(relative);
+			if let Some(parent) = path.parent() {
+				fs::create_dir_all(parent)?;
+			}
+			write_file_safe(path, text)?;
+		}
+		for name in CONTENT_DIRECTORIES.iter() {
+			let path = directory.join(name);
+			if fs::symlink_metadata(&path).map(|meta| meta.is_dir()).unwrap_or(false) {
+				remove_stale_content(&path, &format!("{}/", name), &self.content)?;
+			}
+		}
+		Ok(())
+	}
+
+	/// Check whether all rooms, entities and items in the savegames exist in the given content
+	pub fn check_compatibility(&self, loader: &WorldLoader) -> Result<()> {
+		let meta = loader.load_world_meta()?;
+		let encyclopedia = loader.load_encyclopediae(&meta.encyclopediae)?;
+		if content_hash(&read_content(&loader.directory)?) == self.manifest.content_hash {
+			return Ok(());
+		}
+		let mut problems = Vec::new();
+		for (id, state) in self.rooms.iter() {
+			if *id != purgatory::purgatory_id() {
+				if let Err(err) = loader.load_room(id.clone()) {
+					problems.push(format!("room {} can not be loaded: {}", id, err));
+				}
+			}
+			for template in state.changes.values().flatten() {
+				if let Err(err) = encyclopedia.construct(template) {
+					problems.push(format!("entity in room {} can not be constructed: {}", id, err));
+				}
+			}
+		}
+		for (id, state) in self.players.iter() {
+			if let Err(err) = state.construct(&encyclopedia) {
+				problems.push(format!("player {} can not be constructed: {}", id, err));
+			}
+			if let Some(room) = &state.room {
+				if *room != purgatory::purgatory_id() && loader.load_room(room.clone()).is_err() {
+					problems.push(format!("room {} of player {} can not be loaded", room, id));
+				}
+			}
+		}
+		if problems.is_empty() {
+			Ok(())
+		} else {
+			Err(aerr!("archive is not compatible with the content in {:?}:\n\t{}", loader.directory, problems.join("\n\t")))
+		}
+	}
+
+	/// Write the savegames and users to the storage and registry.
+	/// Unless overwrite is set, this fails when they already have savegames or users, because those would get mixed with the archive.
+	pub fn import(&self, storage: &dyn PersistentStorage, registry: &dyn UserRegistry, overwrite: bool) -> Result<()> {
+		let is_empty = storage.list_rooms()?.is_empty() && storage.list_players()?.is_empty() && registry.list_users()?.is_empty();
+		if !overwrite && !is_empty {
+			return Err(aerr!("the save directory already has savegames or users"));
+		}
+		for (id, state) in self.rooms.iter() {
+			storage.save_room(id.clone(), state.clone())?;
+		}
+		for (id, state) in self.players.iter() {
+			storage.save_player(id.clone(), state.clone())?;
+		}
+		for (id, user) in self.users.iter() {
+			registry.register_user(id, user)?;
+		}
+		if let Some(steps) = self.steps {
+			storage.save_world_meta(Timestamp(steps))?;
+		}
+		storage.flush()
+	}
+}
+
+/// All files in the content directory as text, with their path relative to the content directory as key
+fn read_content(directory: &Path) -> Result<BTreeMap<String, String>> {
+	let mut content = BTreeMap::new();
+	read_content_into(directory, "", &mut content)?;
+	Ok(content)
+}
+
+fn read_content_into(directory: &Path, prefix: &str, content: &mut BTreeMap<String, String>) -> Result<()> {
+	for entry in fs::read_dir(directory)? {
+		let path = entry?.path();
+		let fname = path.file_name().and_then(|f| f.to_str()).ok_or(aerr!("invalid file name in content: {:?}", path))?;
+		let name = format!("{}{}", prefix, fname);
+		if path.is_dir() {
+			read_content_into(&path, &format!("{}/", name), content)?;
+		} else {
+			content.insert(name, fs::read_to_string(&path)?);
+		}
+	}
+	Ok(())
+}
+
+fn remove_stale_content(directory: &Path, prefix: &str, content: &BTreeMap<String, String>) -> Result<()> {
+	for entry in fs::read_dir(directory)? {
+		let entry = entry?;
+		let path = entry.path();
+		let fname = path.file_name().and_then(|f| f.to_str()).ok_or(aerr!("invalid file name in content: {:?}", path))?;
+		let name = format!("{}{}", prefix, fname);
+		// the file type of the entry itself, so symbolic links are not followed
+		let file_type = entry.file_type()?;
+		if file_type.is_dir() {
+			remove_stale_content(&path, &format!("{}/", name), content)?;
+		} else if file_type.is_file() && !content.contains_key(&name) {
+			fs::remove_file(&path)?;
+		}
+	}
+	Ok(())
+}
+
+fn content_hash(content: &BTreeMap<String, String>) -> String {
+	let mut context = digest::Context::new(&digest::SHA256);
+	for (name, text) in content.iter() {
+		context.update(name.as_bytes());
+		context.update(&[0]);
+		context.update(text.as_bytes());
+		context.update(&[0]);
+	}
+	base64::encode(context.finish().as_ref())
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::path::PathBuf;
+	use crate::{
+		persistence::MemoryStorage,
+		auth::{MemoryRegister, UserRole},
+		Template,
+		Pos
+	};
+
+	fn content_loader() -> WorldLoader {
+		WorldLoader::new(PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("content"))
+	}
+
+	fn temp_path(name: &str) -> PathBuf {
+		let path = std::env::temp_dir().join(format!("asciifarm-archive-test-{}-{}", std::process::id(), name));
+		let _ = fs::remove_dir_all(&path);
+		let _ = fs::remove_file(&path);
+		path
+	}
+
+	#[test]
+	fn export_and_import_keep_the_savegames() {
+		let storage = MemoryStorage::default();
+		let registry = MemoryRegister::default();
+		let mut room = SaveState::new();
+		room.time = Some(Timestamp(1234));
+		room.changes.insert(Pos::new(3, 4), vec![Template::empty("stone")]);
+		storage.save_room(RoomId("begin".to_string()), room.clone()).unwrap();
+		let player = PlayerState::new(PlayerId("bob".to_string()));
+		storage.save_player(player.id.clone(), player.clone()).unwrap();
+		storage.save_world_meta(Timestamp(1300)).unwrap();
+		let user = User{name: "bob".to_string(), pass_token: "token".to_string(), salt: "salt".to_string(), role: UserRole::Player};
+		registry.register_user(&player.id, &user).unwrap();
+
+		let path = temp_path("roundtrip");
+		WorldArchive::export(&content_loader(), &storage, &registry).unwrap().write(&path).unwrap();
+		let archive = WorldArchive::read(&path).unwrap();
+		fs::remove_file(&path).unwrap();
+		assert_eq!((archive.manifest.rooms, archive.manifest.players, archive.manifest.users), (1, 1, 1));
+		archive.check_compatibility(&content_loader()).unwrap();
+
+		let imported_storage = MemoryStorage::default();
+		let imported_registry = MemoryRegister::default();
+		archive.import(&imported_storage, &imported_registry, false).unwrap();
+		assert_eq!(imported_storage.load_room(RoomId("begin".to_string())).ok(), Some(room));
+		assert_eq!(imported_storage.load_player(player.id.clone()).ok(), Some(player.clone()));
+		assert_eq!(imported_storage.load_world_meta().ok(), Some(Timestamp(1300)));
+		assert_eq!(imported_registry.load_user(&player.id).ok(), Some(user));
+	}
+
+	#[test]
+	fn written_content_replaces_old_content() {
+		let storage = MemoryStorage::default();
+		let archive = WorldArchive::export(&content_loader(), &storage, &MemoryRegister::default()).unwrap();
+		let directory = temp_path("content");
+		fs::create_dir_all(directory.join("maps/old")).unwrap();
+		fs::write(directory.join("maps/old/stale.json"), "{}").unwrap();
+		fs::write(directory.join("notes.txt"), "keep me").unwrap();
+		let outside = temp_path("outside");
+		fs::create_dir_all(&outside).unwrap();
+		fs::write(outside.join("other.json"), "{}").unwrap();
+		std::os::unix::fs::symlink(&outside, directory.join("maps/linked")).unwrap();
+		archive.write_content(&directory).unwrap();
+		assert!(!directory.join("maps/old/stale.json").exists());
+		assert!(directory.join("notes.txt").exists());
+		assert!(outside.join("other.json").exists());
+		let written = read_content(&directory).unwrap();
+		assert!(archive.content.iter().all(|(name, text)| written.get(name) == Some(text)));
+		fs::remove_dir_all(&directory).unwrap();
+		fs::remove_dir_all(&outside).unwrap();
+	}
+
+	#[test]
+	fn import_refuses_storage_with_savegames() {
+		let storage = MemoryStorage::default();
+		storage.save_player(PlayerId("bob".to_string()), PlayerState::new(PlayerId("bob".to_string()))).unwrap();
+		let archive = WorldArchive::export(&content_loader(), &storage, &MemoryRegister::default()).unwrap();
+		assert!(archive.import(&storage, &MemoryRegister::default(), false).is_err());
+		archive.import(&storage, &MemoryRegister::default(), true).unwrap();
+	}
+
+	#[test]
+	fn content_hash_depends_on_names_and_text() {
+		let mut content = BTreeMap::new();
+		content.insert("world.json".to_string(), "{}".to_string());
+		let hash = content_hash(&content);
+		assert_eq!(hash, content_hash(&content.clone()));
+		let mut renamed = BTreeMap::new();
+		renamed.insert("world2.json".to_string(), "{}".to_string());
+		assert_ne!(hash, content_hash(&renamed));
+		content.insert("world.json".to_string(), "{ }".to_string());
+		assert_ne!(hash, content_hash(&content));
+	}
+}
@@ -30,7 +30,11 @@ impl Assemblage {
 			let mut is_complete = true;
 			let mut compargs = HashMap::new();
 			for paramname in comptype.parameters() {
-				let param = parameters.get(paramname).ok_or(aerr!("missing parameter {} for component {:?}", paramname, comptype))?;
+				let param = match parameters.get(paramname) {
+					Some(param) => param,
+					None if comptype.is_optional(paramname) => continue,
+					None => return Err(aerr!("missing parameter {} for component {:?}", paramname, comptype))
+				};
 				match param.evaluate(&self.arguments, &Template::empty("")) {
 					Err(EvaluationError::MissingArgument(_)) => {is_complete = false;}
 					Err(EvaluationError::Other(msg)) => {return Err(aerr!("invalid value for {}: {}", paramname, msg))}
@@ -45,6 +49,12 @@ impl Assemblage {
 	}
 	
 
+	/// The value of a component parameter when the template does not set any arguments
+	pub fn parameter(&self, comptype: ComponentType, name: &str) -> Option<Parameter> {
+		let (_, parameters) = self.components.iter().find(|(typ, _)| *typ == comptype)?;
+		parameters.get(name)?.evaluate(&self.arguments, &Template::empty("")).ok()
+	}
+	
 	pub fn instantiate(&self, template: &Template) -> AnyResult<Vec<ComponentWrapper>>{
 		let mut arguments = self.arguments.clone();
 		for (key, param) in template.kwargs.clone() {
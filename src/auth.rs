@@ -3,6 +3,8 @@ use std::path::{PathBuf};
 use std::fs;
 use std::env;
 use std::io::ErrorKind;
+use std::collections::HashMap;
+use std::sync::Mutex;
 
 use serde_json;
 use serde::{Serialize, Deserialize};
@@ -12,7 +14,8 @@ use base64;
 use crate::{
 	PlayerId,
 	errors::AnyError,
-	util::write_file_safe
+	aerr,
+	util::{write_file_safe, list_names_with_suffix}
 };
 
 
@@ -58,6 +61,8 @@ pub trait UserRegistry {
 	
 	fn register_user(&self, id: &PlayerId, user: &User) -> Result<(), AnyError>;
 	
+	fn list_users(&self) -> Result<Vec<PlayerId>, AnyError>;
+	
 	fn user_exists(&self, id: &PlayerId) -> bool {
 		match self.load_user(id) {
 			Ok(_) => true,
@@ -122,7 +127,31 @@ impl UserRegistry for FileRegister {
 		write_file_safe(path, text)?;
 		Ok(())
 	}
+	
+	fn list_users(&self) -> Result<Vec<PlayerId>, AnyError> {
+		Ok(list_names_with_suffix(&self.directory, ".auth.json")?.into_iter().map(PlayerId).collect())
+	}
 }
 
 
+/// User registry that only keeps the registrations in memory
+#[derive(Default)]
+pub struct MemoryRegister {
+	users: Mutex<HashMap<PlayerId, User>>
+}
 
+impl UserRegistry for MemoryRegister {
+	
+	fn load_user(&self, id: &PlayerId) -> Result<User, LoaderError> {
+		self.users.lock().unwrap().get(id).cloned().ok_or_else(|| LoaderError::MissingResource(aerr!("user {} not registered", id)))
+	}
+	
+	fn register_user(&self, id: &PlayerId, user: &User) -> Result<(), AnyError> {
+		self.users.lock().unwrap().insert(id.clone(), user.clone());
+		Ok(())
+	}
+	
+	fn list_users(&self) -> Result<Vec<PlayerId>, AnyError> {
+		Ok(self.users.lock().unwrap().keys().cloned().collect())
+	}
+}
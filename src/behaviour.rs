@@ -0,0 +1,224 @@
+
+use std::collections::{HashMap, HashSet};
+use rand::Rng;
+use serde::{Serialize, Deserialize};
+use crate::{
+	Pos,
+	Result,
+	aerr,
+	components::Flag
+};
+
+/// What a creature does while it is in a state
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+	/// stand still
+	Idle,
+	/// walk around randomly, and go home now and then
+	Wander,
+	/// attack the closest enemy, walking towards it when it is out of reach
+	Chase,
+	/// walk away from the closest enemy
+	Flee,
+	/// walk along these positions relative to the anchor, over and over
+	Patrol(Vec<Pos>),
+	/// stay at the anchor and chase enemies that come within this distance of it
+	Guard(i64)
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Condition {
+	/// health is at most this fraction of the maximum health
+	HealthBelow(f64),
+	/// health is more than this fraction of the maximum health
+	HealthAbove(f64),
+	/// an enemy is within the view distance
+	SeesEnemy,
+	/// an enemy is within this distance
+	EnemyWithin(i64),
+	/// the creature is further than this from its anchor
+	FarFromAnchor(i64),
+	/// the creature has been in the current state for at least this many steps
+	After(i64),
+	/// true with this chance every step
+	Chance(f64),
+	/// the room has this flag, like Night
+	Flag(Flag),
+	Not(Box<Condition>)
+}
+
+/// What a creature knows when its conditions are checked
+pub struct Situation<'a> {
+	pub health: Option<(i64, i64)>,
+	pub enemy_distance: Option<i64>,
+	pub anchor_distance: i64,
+	pub time_in_state: i64,
+	pub room_flags: &'a HashSet<Flag>
+}
+
+impl Condition {
+	pub fn holds(&self, situation: &Situation) -> bool {
+		let health_fraction = situation.health.map(|(health, maxhealth)| health as f64 / maxhealth.max(1) as f64);
+		match self {
+			Condition::HealthBelow(fraction) => health_fraction.is_some_and(|health| health <= *fraction),
+			Condition::HealthAbove(fraction) => health_fraction.is_some_and(|health| health > *fraction),
+			Condition::SeesEnemy => situation.enemy_distance.is_some(),
+			Condition::EnemyWithin(distance) => situation.enemy_distance.is_some_and(|enemy| enemy <= *distance),
+			Condition::FarFromAnchor(distance) => situation.anchor_distance > *distance,
+			Condition::After(steps) => situation.time_in_state >= *steps,
+			Condition::Chance(chance) => rand::thread_rng().gen_range(0.0, 1.0) < *chance,
+			Condition::Flag(flag) => situation.room_flags.contains(flag),
+			Condition::Not(condition) => !condition.holds(situation)
+		}
+	}
+}
+
+/// Go to another state when all conditions hold
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Transition {
+	#[serde(default)]
+	pub when: Vec<Condition>,
+	pub to: String
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct State {
+	pub action: Action,
+	/// The first transition of which the conditions hold is taken
+	#[serde(default)]
+	pub transitions: Vec<Transition>
+}
+
+/// A state machine that drives a creature with a MonsterAI
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Behaviour {
+	pub start: String,
+	pub states: HashMap<String, State>
+}
+
+/// Wander around, and chase enemies when they are in view
+impl Default for Behaviour {
+	fn default() -> Self {
+		let state = |action, condition, to: &str| State {
+			action,
+			transitions: vec![Transition{when: vec![condition], to: to.to_string()}]
+		};
+		Self {
+			start: "wander".to_string(),
+			states: vec![
+				("wander".to_string(), state(Action::Wander, Condition::SeesEnemy, "chase")),
+				("chase".to_string(), state(Action::Chase, Condition::Not(Box::new(Condition::SeesEnemy)), "wander"))
+			].into_iter().collect()
+		}
+	}
+}
+
+impl Behaviour {
+
+	pub fn validate(&self) -> Result<()> {
+		if !self.states.contains_key(&self.start) {
+			return Err(aerr!("start state '{}' does not exist", self.start));
+		}
+		for (name, state) in self.states.iter() {
+			if let Some(transition) = state.transitions.iter().find(|transition| !self.states.contains_key(&transition.to)) {
+				return Err(aerr!("state '{}' has a transition to unknown state '{}'", name, transition.to));
+			}
+			if state.action == Action::Patrol(Vec::new()) {
+				return Err(aerr!("state '{}' patrols without waypoints", name));
+			}
+		}
+		Ok(())
+	}
+
+	/// The state to go to from the current state, if any
+	pub fn transition(&self, current: &str, situation: &Situation) -> Option<&str> {
+		self.states.get(current)?
+			.transitions
+			.iter()
+			.find(|transition| transition.when.iter().all(|condition| condition.holds(situation)))
+			.map(|transition| transition.to.as_str())
+	}
+
+	pub fn action(&self, state: &str) -> Option<&Action> {
+		self.states.get(state).map(|state| &state.action)
+	}
+}
+
+/// The behaviours from the encyclopedia by name
+#[derive(Debug, Clone, Default)]
+pub struct BehaviourTable {
+	pub behaviours: HashMap<String, Behaviour>,
+	pub default: Behaviour
+}
+
+impl BehaviourTable {
+
+	pub fn new(behaviours: HashMap<String, Behaviour>) -> Self {
+		Self {
+			behaviours,
+			default: Behaviour::default()
+		}
+	}
+
+	/// Creatures without a (known) behaviour get the default behaviour
+	pub fn get(&self, name: Option<&str>) -> &Behaviour {
+		name.and_then(|name| self.behaviours.get(name)).unwrap_or(&self.default)
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use serde_json::json;
+
+	fn coward() -> Behaviour {
+		Behaviour::deserialize(json!({
+			"start": "wander",
+			"states": {
+				"wander": {"action": "wander", "transitions": [{"when": ["sees_enemy"], "to": "chase"}]},
+				"chase": {"action": "chase", "transitions": [
+					{"when": [{"health_below": 0.3}], "to": "flee"},
+					{"when": [{"not": "sees_enemy"}], "to": "wander"}
+				]},
+				"flee": {"action": "flee", "transitions": [{"when": [{"not": {"enemy_within": 5}}, {"after": 20}], "to": "wander"}]},
+				"patrol": {"action": {"patrol": [[0, 0], [4, 0]]}}
+			}
+		})).unwrap()
+	}
+
+	fn situation(flags: &HashSet<Flag>, health: i64, enemy_distance: Option<i64>, time_in_state: i64) -> Situation<'_> {
+		Situation {
+			health: Some((health, 10)),
+			enemy_distance,
+			anchor_distance: 0,
+			time_in_state,
+			room_flags: flags
+		}
+	}
+
+	#[test]
+	fn transitions_follow_conditions() {
+		let behaviour = coward();
+		assert!(behaviour.validate().is_ok());
+		let flags = HashSet::new();
+		assert_eq!(behaviour.transition("wander", &situation(&flags, 10, None, 0)), None);
+		assert_eq!(behaviour.transition("wander", &situation(&flags, 10, Some(4), 0)), Some("chase"));
+		assert_eq!(behaviour.transition("chase", &situation(&flags, 2, Some(1), 0)), Some("flee"));
+		assert_eq!(behaviour.transition("flee", &situation(&flags, 2, Some(6), 5)), None);
+		assert_eq!(behaviour.transition("flee", &situation(&flags, 2, Some(6), 20)), Some("wander"));
+		assert_eq!(behaviour.action("patrol"), Some(&Action::Patrol(vec![Pos::new(0, 0), Pos::new(4, 0)])));
+	}
+
+	#[test]
+	fn invalid_behaviours() {
+		let mut behaviour = coward();
+		behaviour.states.get_mut("flee").unwrap().transitions[0].to = "hide".to_string();
+		assert!(behaviour.validate().is_err());
+		behaviour.start = "sleep".to_string();
+		assert!(behaviour.validate().is_err());
+		assert!(Behaviour::default().validate().is_ok());
+	}
+}
@@ -0,0 +1,102 @@
+
+use serde::{Serialize, Deserialize};
+use strum_macros::{EnumString, Display};
+use crate::{
+	Timestamp,
+	components::Flag
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, EnumString, Display, Serialize, Deserialize)]
+pub enum Season {
+	Spring,
+	Summer,
+	Autumn,
+	Winter
+}
+
+impl Season {
+	
+	pub const ALL: [Season; 4] = [Season::Spring, Season::Summer, Season::Autumn, Season::Winter];
+	
+	pub fn flag(self) -> Flag {
+		match self {
+			Season::Spring => Flag::Spring,
+			Season::Summer => Flag::Summer,
+			Season::Autumn => Flag::Autumn,
+			Season::Winter => Flag::Winter
+		}
+	}
+}
+
+/// Turns the step counter into days and times of day
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Clock {
+	/// The number of steps in a day
+	pub day_length: i64,
+	/// The fraction of the day at which the night ends (0 is midnight)
+	pub dawn: f64,
+	/// The fraction of the day at which the night starts
+	pub dusk: f64,
+	/// The number of days in a season
+	pub season_length: i64
+}
+
+impl Default for Clock {
+	fn default() -> Self {
+		Self {
+			day_length: 12000,
+			dawn: 0.25,
+			dusk: 0.75,
+			season_length: 7
+		}
+	}
+}
+
+impl Clock {
+
+	pub fn day(&self, time: Timestamp) -> i64 {
+		time.0.div_euclid(self.day_length.max(1))
+	}
+
+	/// The fraction of the day that has passed since midnight
+	pub fn time_of_day(&self, time: Timestamp) -> f64 {
+		time.0.rem_euclid(self.day_length.max(1)) as f64 / self.day_length.max(1) as f64
+	}
+
+	pub fn is_night(&self, time: Timestamp) -> bool {
+		let time_of_day = self.time_of_day(time);
+		time_of_day < self.dawn || time_of_day >= self.dusk
+	}
+	
+	pub fn season(&self, time: Timestamp) -> Season {
+		Season::ALL[self.day(time).div_euclid(self.season_length.max(1)).rem_euclid(4) as usize]
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn night_is_around_midnight() {
+		let clock = Clock{day_length: 100, dawn: 0.25, dusk: 0.75, season_length: 7};
+		assert!(clock.is_night(Timestamp(0)));
+		assert!(clock.is_night(Timestamp(24)));
+		assert!(!clock.is_night(Timestamp(25)));
+		assert!(!clock.is_night(Timestamp(74)));
+		assert!(clock.is_night(Timestamp(175)));
+		assert_eq!(clock.day(Timestamp(175)), 1);
+		assert_eq!(clock.time_of_day(Timestamp(150)), 0.5);
+	}
+	
+	#[test]
+	fn seasons_follow_each_other() {
+		let clock = Clock{day_length: 10, dawn: 0.25, dusk: 0.75, season_length: 2};
+		assert_eq!(clock.season(Timestamp(19)), Season::Spring);
+		assert_eq!(clock.season(Timestamp(20)), Season::Summer);
+		assert_eq!(clock.season(Timestamp(79)), Season::Winter);
+		assert_eq!(clock.season(Timestamp(80)), Season::Spring);
+	}
+}
@@ -27,12 +27,31 @@ pub enum Notification {
 		actor: String,
 		target: String,
 		amount: i64,
+		/// The damage that was stopped by the defence of the target
+		mitigated: i64,
 		typ: HealthNotification
 	},
 	Kill {
 		actor: String,
 		target: String
 	},
+	Progress {
+		skill: String,
+		level: i64,
+		experience: i64,
+		next_level: i64
+	},
+	LevelUp {
+		skill: String,
+		level: i64
+	},
+	Effect {
+		actor: String,
+		target: String,
+		effect: String,
+		amount: i64,
+		duration: i64
+	},
 	Die {
 		actor: String,
 		target: String
@@ -56,12 +75,15 @@ impl Notification {
 	pub fn type_name(&self) -> String {
 		(match self {
 			Sound{source: _, text: _} => "sound",
-			Health{actor: _, target: _, amount: _, typ} => match typ {
+			Health{typ, ..} => match typ {
 				Attack => "attack",
 				Damage => "damage",
 				Heal => "heal"
 			},
 			Kill{actor: _, target: _} => "kill",
+			Effect{..} => "effect",
+			Progress{..} => "progress",
+			LevelUp{..} => "levelup",
 			Die{actor: _, target: _} => "die",
 			Options{description: _, options: _} => "options",
 			Describe{name: _, description: _} => "describe",
@@ -78,17 +100,30 @@ impl Notification {
 				},
 				json!({"source": source, "text": text})
 			)}
-			Health{actor, target, amount, typ} => {(
+			Health{actor, target, amount, mitigated, typ} => {(
 				match typ {
+					Attack | Damage if *mitigated > 0 => format!("{} attacks {} for {} damage ({} blocked)", actor, target, amount, mitigated),
 					Attack | Damage => format!("{} attacks {} for {} damage", actor, target, amount),
 					Heal => format!("{} heals {} for {} health", actor, target, amount)
 				},
-				json!({"actor": actor.clone(), "target": target.clone(), "amount": amount})
+				json!({"actor": actor.clone(), "target": target.clone(), "amount": amount, "mitigated": mitigated})
 			)},
 			Kill{actor, target} => {(
 				format!("{} kills {}", actor, target),
 				json!({"actor": actor.clone(), "target": target.clone()})
 			)},
+			Progress{skill, level, experience, next_level} => {(
+				format!("{} level {}: {}/{} experience", skill, level, experience, next_level),
+				json!({"skill": skill.clone(), "level": level, "experience": experience, "next_level": next_level})
+			)},
+			LevelUp{skill, level} => {(
+				format!("Your {} is now level {}", skill, level),
+				json!({"skill": skill.clone(), "level": level})
+			)},
+			Effect{actor, target, effect, amount, duration} => {(
+				format!("{} is affected by {} {} for {} steps", target, effect, amount, duration),
+				json!({"actor": actor.clone(), "target": target.clone(), "effect": effect.clone(), "amount": amount, "duration": duration})
+			)},
 			Die{actor, target} => {(
 				format!("{} was killed by {}", target, actor),
 				json!({"actor": actor.clone(), "target": target.clone()})
@@ -0,0 +1,197 @@
+
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fmt;
+use std::str::FromStr;
+use serde::{Serialize, Deserialize};
+use specs::{
+	Component,
+	HashMapStorage
+};
+use crate::Timestamp;
+use super::equipment::Stat;
+
+/// Poison and regeneration change the health once every this many steps
+pub const EFFECT_INTERVAL: i64 = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EffectKind {
+	/// lose health over time
+	Poison,
+	/// gain health over time
+	Regeneration,
+	/// extra cooldown after moving
+	Slow,
+	/// bonus (or penalty) to a stat
+	Stat(Stat)
+}
+
+impl FromStr for EffectKind {
+	type Err = strum::ParseError;
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		Ok(match s {
+			"poison" => EffectKind::Poison,
+			"regeneration" => EffectKind::Regeneration,
+			"slow" => EffectKind::Slow,
+			stat => EffectKind::Stat(Stat::from_str(stat)?)
+		})
+	}
+}
+
+impl fmt::Display for EffectKind {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			EffectKind::Poison => write!(f, "poison"),
+			EffectKind::Regeneration => write!(f, "regeneration"),
+			EffectKind::Slow => write!(f, "slow"),
+			EffectKind::Stat(stat) => write!(f, "{}", stat)
+		}
+	}
+}
+
+/// A status effect as it is written in the content: [kind, amount, duration in steps]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "(String, i64, i64)", into = "(String, i64, i64)")]
+pub struct Effect {
+	pub kind: EffectKind,
+	pub amount: i64,
+	pub duration: i64
+}
+
+impl Effect {
+	pub fn is_harmful(&self) -> bool {
+		match self.kind {
+			EffectKind::Poison | EffectKind::Slow => self.amount > 0,
+			EffectKind::Regeneration | EffectKind::Stat(_) => self.amount < 0
+		}
+	}
+}
+
+impl TryFrom<(String, i64, i64)> for Effect {
+	type Error = String;
+	fn try_from((kind, amount, duration): (String, i64, i64)) -> Result<Self, Self::Error> {
+		Ok(Effect {
+			kind: EffectKind::from_str(&kind).map_err(|_| format!("invalid effect kind '{}'", kind))?,
+			amount,
+			duration
+		})
+	}
+}
+
+impl From<Effect> for (String, i64, i64) {
+	fn from(effect: Effect) -> Self {
+		(effect.kind.to_string(), effect.amount, effect.duration)
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ActiveEffect {
+	pub effect: Effect,
+	pub until: Timestamp
+}
+
+#[derive(Component, Debug, Clone, Default, PartialEq)]
+#[storage(HashMapStorage)]
+pub struct StatusEffects {
+	pub effects: Vec<ActiveEffect>
+}
+
+impl StatusEffects {
+
+	/// A new effect replaces an active effect of the same kind
+	pub fn add(&mut self, effect: Effect, now: Timestamp) {
+		self.effects.retain(|active| active.effect.kind != effect.kind);
+		self.effects.push(ActiveEffect{effect, until: now + effect.duration});
+	}
+
+	pub fn expire(&mut self, now: Timestamp) {
+		self.effects.retain(|active| active.until > now);
+	}
+
+	fn total(&self, kind: EffectKind) -> i64 {
+		self.effects.iter().filter(|active| active.effect.kind == kind).map(|active| active.effect.amount).sum()
+	}
+
+	pub fn stat_bonuses(&self) -> HashMap<Stat, i64> {
+		let mut bonuses = HashMap::new();
+		for active in self.effects.iter() {
+			if let EffectKind::Stat(stat) = active.effect.kind {
+				*bonuses.entry(stat).or_insert(0) += active.effect.amount;
+			}
+		}
+		bonuses
+	}
+
+	pub fn slow(&self) -> i64 {
+		self.total(EffectKind::Slow).max(0)
+	}
+
+	/// The change in health in this step from poison and regeneration
+	pub fn health_change(&self, now: Timestamp) -> (i64, i64) {
+		if now.0 % EFFECT_INTERVAL != 0 {
+			return (0, 0);
+		}
+		(self.total(EffectKind::Poison), self.total(EffectKind::Regeneration))
+	}
+
+	/// The total change in health from poison and regeneration in the steps after `from` and before `to`
+	pub fn health_change_between(&self, from: Timestamp, to: Timestamp) -> (i64, i64) {
+		let mut poison = 0;
+		let mut regeneration = 0;
+		for active in self.effects.iter() {
+			let end = std::cmp::min(active.until, to);
+			if end <= from {
+				continue;
+			}
+			let steps = (end.0 - 1).div_euclid(EFFECT_INTERVAL) - from.0.div_euclid(EFFECT_INTERVAL);
+			match active.effect.kind {
+				EffectKind::Poison => poison += active.effect.amount * steps,
+				EffectKind::Regeneration => regeneration += active.effect.amount * steps,
+				_ => {}
+			}
+		}
+		(poison, regeneration)
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use serde_json::json;
+
+	#[test]
+	fn effect_deserialize() {
+		assert_eq!(
+			Effect::deserialize(&json!(["strength", 5, 100])).unwrap(),
+			Effect{kind: EffectKind::Stat(Stat::Strength), amount: 5, duration: 100}
+		);
+		assert_eq!(Effect::deserialize(&json!(["poison", 1, 50])).unwrap().kind, EffectKind::Poison);
+		assert!(Effect::deserialize(&json!(["fire", 1, 50])).is_err());
+		assert!(Effect::deserialize(&json!(["poison", 1])).is_err());
+	}
+
+	#[test]
+	fn effects_expire_and_replace() {
+		let mut effects = StatusEffects::default();
+		effects.add(Effect{kind: EffectKind::Stat(Stat::Strength), amount: 5, duration: 100}, Timestamp(0));
+		effects.add(Effect{kind: EffectKind::Slow, amount: 2, duration: 20}, Timestamp(0));
+		effects.add(Effect{kind: EffectKind::Slow, amount: 3, duration: 20}, Timestamp(10));
+		assert_eq!(effects.slow(), 3);
+		assert_eq!(effects.stat_bonuses().get(&Stat::Strength), Some(&5));
+		effects.expire(Timestamp(30));
+		assert_eq!(effects.slow(), 0);
+		effects.expire(Timestamp(100));
+		assert!(effects.effects.is_empty());
+	}
+
+	#[test]
+	fn health_change_between_counts_intervals() {
+		let mut effects = StatusEffects::default();
+		effects.add(Effect{kind: EffectKind::Poison, amount: 2, duration: 45}, Timestamp(5));
+		effects.add(Effect{kind: EffectKind::Regeneration, amount: 1, duration: 1000}, Timestamp(5));
+		// poison ends at 50, so it is active at 10, 20, 30 and 40, and regeneration at every interval before 100
+		assert_eq!(effects.health_change_between(Timestamp(5), Timestamp(100)), (8, 9));
+		assert_eq!(effects.health_change_between(Timestamp(50), Timestamp(100)), (0, 4));
+	}
+}
@@ -23,11 +23,26 @@ pub enum Slot {
 pub enum Stat {
 	Strength,
 	Defence,
+	/// Extra distance that projectiles fly
+	Range,
 	Mining,
 	Gathering
 }
 
 
+/// The amount of defence that halves the damage of a hit
+pub const DEFENCE_SCALE: i64 = 10;
+
+/// The damage that is left of a hit after the defence of the target
+pub fn mitigate(damage: i64, defence: i64) -> i64 {
+	if damage <= 0 {
+		return 0;
+	}
+	let scale = DEFENCE_SCALE as f64;
+	(damage as f64 * scale / (scale + defence.max(0) as f64)).round() as i64
+}
+
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Equippable {
 	pub slot: Slot,
@@ -63,6 +78,27 @@ mod tests {
 		assert!(Stat::from_str("attack").is_err());
 	}
 	
+	#[test]
+	fn defence_mitigates_damage() {
+		assert_eq!(mitigate(10, 0), 10);
+		assert_eq!(mitigate(10, DEFENCE_SCALE), 5);
+		assert_eq!(mitigate(10, 3), 8);
+		assert_eq!(mitigate(100, 90), 10);
+		assert_eq!(mitigate(0, 3), 0);
+		assert_eq!(mitigate(-5, 0), 0);
+		assert_eq!(mitigate(10, -5), 10);
+	}
+	
+	#[test]
+	fn more_defence_never_increases_damage() {
+		for damage in 0..50 {
+			for defence in 0..50 {
+				assert!(mitigate(damage, defence + 1) <= mitigate(damage, defence));
+				assert!(mitigate(damage, defence) <= damage);
+			}
+		}
+	}
+	
 	#[test]
 	fn equippable_deserialize() {
 		assert_eq!(
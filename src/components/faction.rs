@@ -1,36 +1,152 @@
-
-use strum_macros::{EnumString, Display};
+use std::collections::HashMap;
+use serde::{Serialize, Deserialize};
 use specs::{
 	Component,
 	HashMapStorage,
+	NullStorage,
 	ReadStorage,
 	Entity,
 };
+use super::Player;
+use crate::{Result, aerr};
 
-#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Hash, EnumString, Display)]
-#[strum(serialize_all = "lowercase")]
+#[derive(Component, Debug, Clone, PartialEq, Eq, Hash)]
 #[storage(HashMapStorage)]
-pub enum Faction {
+pub struct Faction {
+	pub name: String
+}
+
+/// Entities without a Faction component belong to this faction
+pub const NO_FACTION: &str = "none";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Relation {
+	Hostile,
 	Neutral,
-	Good,
-	Evil,
-	None
+	Friendly
 }
 
-use Faction::{Neutral, Good, Evil, None};
+/// How factions see each other.
+/// Relations hold in both directions. Unlisted factions are neutral to each other and friendly to themselves.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FactionTable(pub HashMap<String, HashMap<String, Relation>>);
 
-impl Faction {
-	
-	pub fn is_enemy(&self, other: Faction) -> bool {
-		match self {
-			Neutral => false,
-			Good => other == Evil || other == None,
-			Evil => other == Good || other == None,
-			None => other != Neutral
+impl Default for FactionTable {
+	fn default() -> Self {
+		let hostile = |names: &[&str]| names.iter().map(|name| (name.to_string(), Relation::Hostile)).collect();
+		Self(vec![
+			("neutral".to_string(), HashMap::new()),
+			("good".to_string(), hostile(&["evil", NO_FACTION])),
+			("evil".to_string(), hostile(&["good", NO_FACTION])),
+			(NO_FACTION.to_string(), hostile(&[NO_FACTION]))
+		].into_iter().collect())
+	}
+}
+
+impl FactionTable {
+
+	pub fn relation(&self, a: &str, b: &str) -> Relation {
+		let declared = |from: &str, to: &str| self.0.get(from).and_then(|relations| relations.get(to)).copied();
+		declared(a, b)
+			.or_else(|| declared(b, a))
+			.unwrap_or(if a == b {Relation::Friendly} else {Relation::Neutral})
+	}
+
+	/// Declaring a relation in both directions is only allowed when both are the same
+	pub fn validate(&self) -> Result<()> {
+		for (name, relations) in self.0.iter() {
+			for (other_name, relation) in relations {
+				if let Some(reverse) = self.0.get(other_name).and_then(|reverse| reverse.get(name)) {
+					if reverse != relation {
+						return Err(aerr!("contradicting relations between factions {} and {}", name, other_name));
+					}
+				}
+			}
 		}
+		Ok(())
+	}
+
+	/// Relations in other override relations in self
+	pub fn merge(&mut self, other: FactionTable) {
+		for (name, relations) in other.0 {
+			for (other_name, relation) in relations {
+				// make sure an override is not shadowed by the reverse entry
+				if let Some(reverse) = self.0.get_mut(&other_name) {
+					reverse.remove(&name);
+				}
+				self.0.entry(name.clone()).or_default().insert(other_name, relation);
+			}
+		}
+	}
+}
+
+impl Faction {
+
+	fn name_of<'a>(factions: &'a ReadStorage<Self>, ent: Entity) -> &'a str {
+		factions.get(ent).map(|faction| faction.name.as_str()).unwrap_or(NO_FACTION)
 	}
-	
-	pub fn is_enemy_entity(factions: &ReadStorage<Self>, a: Entity, b: Entity) -> bool{
-		factions.get(a).unwrap_or(&None).is_enemy(*factions.get(b).unwrap_or(&None))
+
+	pub fn relation_entity(factions: &ReadStorage<Self>, table: &FactionTable, a: Entity, b: Entity) -> Relation {
+		table.relation(Self::name_of(factions, a), Self::name_of(factions, b))
+	}
+
+	pub fn is_ally_entity(factions: &ReadStorage<Self>, table: &FactionTable, a: Entity, b: Entity) -> bool {
+		Self::relation_entity(factions, table, a, b) == Relation::Friendly
+	}
+
+	pub fn is_enemy_entity(factions: &ReadStorage<Self>, table: &FactionTable, a: Entity, b: Entity) -> bool {
+		Self::relation_entity(factions, table, a, b) == Relation::Hostile
+	}
+}
+
+/// Marks players that opted in to fight other players
+#[derive(Component, Debug, Clone, Default)]
+#[storage(NullStorage)]
+pub struct Pvp;
+
+impl Pvp {
+
+	/// Players can fight each other when the room forces it or when both opted in
+	pub fn allowed(pvps: &ReadStorage<Self>, players: &ReadStorage<Player>, forced: bool, a: Entity, b: Entity) -> bool {
+		a != b && players.contains(a) && players.contains(b) && (forced || (pvps.contains(a) && pvps.contains(b)))
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use serde_json::json;
+
+	#[test]
+	fn default_relations() {
+		let table = FactionTable::default();
+		assert_eq!(table.relation("good", "evil"), Relation::Hostile);
+		assert_eq!(table.relation("evil", "good"), Relation::Hostile);
+		assert_eq!(table.relation("none", "none"), Relation::Hostile);
+		assert_eq!(table.relation("neutral", "evil"), Relation::Neutral);
+		assert_eq!(table.relation("good", "good"), Relation::Friendly);
+		assert_eq!(table.relation("good", "wolves"), Relation::Neutral);
+	}
+
+	#[test]
+	fn merged_relations_override() {
+		let mut table = FactionTable::default();
+		table.merge(FactionTable::deserialize(json!({
+			"wolves": {"evil": "hostile"},
+			"good": {"evil": "neutral"}
+		})).unwrap());
+		assert_eq!(table.relation("evil", "wolves"), Relation::Hostile);
+		assert_eq!(table.relation("evil", "good"), Relation::Neutral);
+		assert_eq!(table.relation("good", "none"), Relation::Hostile);
+	}
+
+	#[test]
+	fn contradicting_relations_are_invalid() {
+		let table = |value| FactionTable::deserialize(value).unwrap();
+		assert!(FactionTable::default().validate().is_ok());
+		assert!(table(json!({"wolves": {"sheep": "hostile"}, "sheep": {"wolves": "hostile"}})).validate().is_ok());
+		assert!(table(json!({"wolves": {"sheep": "hostile"}, "sheep": {"wolves": "friendly"}})).validate().is_err());
 	}
 }
@@ -14,7 +14,22 @@ pub enum Flag {
 	Occupied,
 	Soil,
 	Build,
-	Hot
+	Hot,
+	/// room flag: no fighting or traps
+	Peaceful,
+	/// room flag: players can always fight each other
+	Pvp,
+	/// room flag: spawners do not spawn
+	NoSpawn,
+	/// room flag, set by the clock
+	Day,
+	/// room flag, set by the clock
+	Night,
+	/// room flags, set by the clock
+	Spring,
+	Summer,
+	Autumn,
+	Winter
 }
 
 
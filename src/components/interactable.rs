@@ -23,6 +23,10 @@ pub enum Interactable {
 	Say(String),
 	Reply(String),
 	Exchange(String, HashMap<String, Exchange>),
+	/// Take the items out of a grave
+	Grave,
+	/// Make this the place where the player respawns
+	Bind,
 }
 
 use Interactable::*;
@@ -36,6 +40,8 @@ impl Interactable {
 			("mine", Parameter::String(s)) => Mine(Stat::from_str(s).ok()?),
 			("say", Parameter::String(s)) => Say(s.clone()),
 			("reply", Parameter::String(s)) => Reply(s.clone()),
+			("grave", _) => Grave,
+			("bind", _) => Bind,
 			("exchange", p) => {
 				let (prefix, trades) = <(String, Vec<(String, Vec<ItemId>, Vec<ItemId>)>)>::from_parameter(p.clone())?;
 				let exchanges = trades.into_iter().map(|(k, cost, offer)| (k, Exchange{cost, offer})).collect();
@@ -57,6 +63,8 @@ impl Interactable {
 			}
 			Mine(_) => arg.is_none(),
 			Say(_) => arg.is_none(),
+			Grave => arg.is_none(),
+			Bind => arg.is_none(),
 			Reply(_) => arg.is_some(),
 			Exchange(prefix, _exchanges) => {
 				if let Some(txt) = arg {
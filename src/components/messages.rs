@@ -9,6 +9,7 @@ use specs::{
 	WriteStorage
 };
 use super::equipment::Stat;
+use super::effect::Effect;
 
 
 
@@ -50,20 +51,26 @@ impl <M: Message> Inbox<M> {
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum AttackType {
 	Attack(i64),
-	Heal(i64)
+	Heal(i64),
+	/// Damage that is not rolled and not mitigated by defence
+	Poison(i64),
+	/// Apply a status effect
+	Effect(Effect)
 }
 
 impl AttackType {
 	pub fn is_hostile(&self) -> bool {
 		match self {
-			Self::Attack(_) => true,
-			Self::Heal(_) => false
+			Self::Attack(_) | Self::Poison(_) => true,
+			Self::Heal(_) => false,
+			Self::Effect(effect) => effect.is_harmful()
 		}
 	}
+	/// Only strength adds to the damage of an attack; other stats don't change attacks
 	pub fn apply_bonuses(self, bonuses: &HashMap<Stat, i64>) -> AttackType {
 		match self {
 			Self::Attack(strength) => Self::Attack(strength + *bonuses.get(&Stat::Strength).unwrap_or(&0)),
-			Self::Heal(_) => self
+			_ => self
 		}
 	}
 }
@@ -78,6 +85,16 @@ impl Message for AttackMessage {}
 
 pub type AttackInbox = Inbox<AttackMessage>;
 
+impl AttackInbox {
+	/// Send an attack together with the status effects that it applies
+	pub fn add_attack(inboxes: &mut WriteStorage<Self>, target: Entity, attack: AttackType, effects: &[Effect], attacker: Option<Entity>) {
+		Self::add_message(inboxes, target, AttackMessage{typ: attack, attacker});
+		for effect in effects {
+			Self::add_message(inboxes, target, AttackMessage{typ: AttackType::Effect(*effect), attacker});
+		}
+	}
+}
+
 
 
 
@@ -7,6 +7,7 @@ pub mod inventory;
 pub mod serialise;
 pub mod flags;
 pub mod ear;
+pub mod effect;
 
 pub use messages::{
 	AttackMessage,
@@ -15,7 +16,7 @@ pub use messages::{
 	Trigger,
 	TriggerBox
 };
-pub use faction::Faction;
+pub use faction::{Faction, Pvp};
 pub use interactable::{Interactable};
 pub use equipment::{Equippable, Stat, Slot};
 pub use inventory::Inventory;
@@ -24,9 +25,11 @@ pub use flags::{
 	Flag,
 	Flags
 };
+pub use effect::{Effect, StatusEffects};
 pub use ear::{
 	Notification,
-	Ear
+	Ear,
+	say
 };
 
 use std::collections::{HashMap, HashSet};
@@ -37,7 +40,8 @@ use specs::{
 	HashMapStorage,
 	NullStorage,
 	Component,
-	Entity
+	Entity,
+	WriteStorage
 };
 
 use crate::{
@@ -45,10 +49,11 @@ use crate::{
 	PlayerId,
 	RoomId,
 	Sprite,
-	controls::Control,
+	controls::{Control, Direction},
 	Template,
 	playerstate::RoomPos,
 	Timestamp,
+	clock::Season,
 	ItemId,
 };
 
@@ -132,7 +137,8 @@ pub struct Entered;
 #[derive(Component, Debug, Clone)]
 #[storage(HashMapStorage)]
 pub struct Trap {
-	pub attack: AttackType
+	pub attack: AttackType,
+	pub effects: Vec<Effect>
 }
 
 #[derive(Component, Debug, Clone)]
@@ -140,7 +146,19 @@ pub struct Trap {
 pub struct Fighter {
 	pub attack: AttackType,
 	pub cooldown: i64,
-	pub range: i64
+	pub range: i64,
+	pub effects: Vec<Effect>
+}
+
+/// Flies one step in its direction every step until it hits something with health, runs into a wall or has flown its range
+#[derive(Component, Debug, Clone)]
+#[storage(HashMapStorage)]
+pub struct Projectile {
+	pub attack: AttackType,
+	pub direction: Direction,
+	pub range: i64,
+	pub effects: Vec<Effect>,
+	pub attacker: Option<Entity>
 }
 
 #[derive(Component, Debug, Clone)]
@@ -170,7 +188,34 @@ pub struct MonsterAI {
 	pub move_chance: f64,
 	pub view_distance: i64,
 	pub homesickness: f64,
-	pub home: Option<Pos>
+	pub home: Option<Pos>,
+	/// The name of a behaviour in the encyclopedia
+	pub behaviour: Option<String>,
+	/// The current state of the behaviour; extract it to keep it after a reload
+	pub state: Option<String>,
+	/// Patrols and guards stay around this position: the home, or where the creature was when it started.
+	/// Extract it to keep it after a reload
+	pub anchor: Option<Pos>
+}
+
+/// How far a creature is in the current state of its behaviour
+#[derive(Component, Debug, Clone)]
+#[storage(HashMapStorage)]
+pub struct BehaviourState {
+	pub since: Timestamp,
+	/// The index of the next patrol waypoint
+	pub waypoint: usize
+}
+
+/// The cached path of an entity that walks towards a goal
+#[derive(Component, Debug, Clone, Default)]
+#[storage(HashMapStorage)]
+pub struct Route {
+	pub goal: Pos,
+	/// the remaining steps, with the next step last
+	pub steps: Vec<Pos>,
+	/// when the goal could not be reached: the time after which it is searched again
+	pub retry_at: Option<Timestamp>
 }
 
 
@@ -181,7 +226,11 @@ pub struct Spawner {
 	pub clan: Clan,
 	pub template: Template,
 	pub saturated: bool,
-	pub radius: i64
+	pub radius: i64,
+	/// room flags that are needed to spawn, like Night
+	pub required_flags: HashSet<Flag>,
+	/// templates that are spawned instead of the normal template in some seasons
+	pub season_templates: HashMap<Season, Template>
 }
 
 #[derive(Component, Debug, Clone, PartialEq, Eq, Hash)]
@@ -211,7 +260,9 @@ pub struct Timer {
 	pub delay: i64,
 	pub spread: f64,
 	pub target_time: Option<Timestamp>,
-	pub trigger: Trigger
+	pub trigger: Trigger,
+	/// the delay is multiplied by the factor for the season in which the timer starts
+	pub season_factors: HashMap<Season, f64>
 }
 
 #[derive(Component, Debug, Clone)]
@@ -231,6 +282,24 @@ pub struct Build {
 	pub obj: Template
 }
 
+/// Holds the items of a dead player
+#[derive(Component, Debug, Clone)]
+#[storage(HashMapStorage)]
+pub struct Grave {
+	pub owner: PlayerId,
+	pub items: Vec<ItemId>,
+	/// Until this time only the owner can take the items
+	pub public_at: Timestamp
+}
+
+/// Where a player comes back after dying
+#[derive(Component, Debug, Clone)]
+#[storage(HashMapStorage)]
+pub struct RespawnPoint {
+	pub room: RoomId,
+	pub pos: Pos
+}
+
 #[derive(Component, Debug, Clone)]
 pub struct Whitelist{
 	pub allowed: HashMap<String, HashSet<PlayerId>>
@@ -256,7 +325,68 @@ pub struct Substitute {
 
 #[derive(Component, Debug, Clone)]
 pub struct Stats {
-	pub skills: HashMap<Stat, i64>
+	pub skills: HashMap<Stat, i64>,
+	pub experience: HashMap<Stat, i64>
+}
+
+/// Level n takes EXPERIENCE_SCALE * n * n experience
+pub const EXPERIENCE_SCALE: i64 = 25;
+
+impl Stats {
+	
+	pub fn level_for(experience: i64) -> i64 {
+		((experience.max(0) as f64 / EXPERIENCE_SCALE as f64).sqrt() + 1e-9).floor() as i64
+	}
+	
+	pub fn level(&self, stat: Stat) -> i64 {
+		Self::level_for(*self.experience.get(&stat).unwrap_or(&0))
+	}
+	
+	/// How many tenths of the way from its level to the next level this experience is
+	fn tenths(experience: i64) -> i64 {
+		let level = Self::level_for(experience);
+		let start = EXPERIENCE_SCALE * level * level;
+		let next = EXPERIENCE_SCALE * (level + 1) * (level + 1);
+		10 * (experience.max(0) - start) / (next - start)
+	}
+	
+	/// Add experience and tell the entity about its progress for every tenth of a level
+	pub fn train(stats: &mut WriteStorage<Stats>, ears: &mut WriteStorage<Ear>, ent: Entity, stat: Stat, amount: i64) {
+		if let Some(stats) = stats.get_mut(ent) {
+			let old_level = stats.level(stat);
+			let experience = stats.experience.entry(stat).or_insert(0);
+			let old_tenths = Self::tenths(*experience);
+			*experience += amount;
+			let experience = *experience;
+			let level = stats.level(stat);
+			if level != old_level || Self::tenths(experience) != old_tenths {
+				say(ears, ent, Notification::Progress{
+					skill: stat.to_string(),
+					level,
+					experience,
+					next_level: EXPERIENCE_SCALE * (level + 1) * (level + 1)
+				});
+			}
+			if level > old_level {
+				say(ears, ent, Notification::LevelUp{skill: stat.to_string(), level});
+			}
+		}
+	}
+
+	/// The skills of an entity together with its levels and the bonuses of its equipment and status effects
+	pub fn total(stats: Option<&Stats>, inventory: Option<&Inventory>, effects: Option<&StatusEffects>) -> HashMap<Stat, i64> {
+		let mut total = inventory.map(Inventory::equipment_bonuses).unwrap_or_default();
+		let levels = stats.map(|stats| stats.experience.keys().map(|stat| (*stat, stats.level(*stat))).collect());
+		let bonuses = stats.map(|stats| stats.skills.clone()).into_iter()
+			.chain(levels)
+			.chain(effects.map(StatusEffects::stat_bonuses));
+		for bonus in bonuses {
+			for (stat, value) in bonus {
+				*total.entry(stat).or_insert(0) += value;
+			}
+		}
+		total
+	}
 }
 
 #[derive(Component, Debug, Clone)]
@@ -269,3 +399,30 @@ pub struct Requirements {
 pub struct Description {
 	pub description: String
 }
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use specs::{World, WorldExt, Builder};
+	
+	#[test]
+	fn training_notifies_every_tenth_of_a_level() {
+		let mut world = World::new();
+		world.register::<Stats>();
+		world.register::<Ear>();
+		let ent = world.create_entity()
+			.with(Stats{skills: HashMap::new(), experience: HashMap::new()})
+			.with(Ear::default())
+			.build();
+		for _ in 0..EXPERIENCE_SCALE {
+			Stats::train(&mut world.write_component(), &mut world.write_component(), ent, Stat::Strength, 1);
+		}
+		let ears = world.read_component::<Ear>();
+		let sounds = &ears.get(ent).unwrap().sounds;
+		let progress = sounds.iter().filter(|sound| matches!(sound, Notification::Progress{..})).count();
+		let level_ups = sounds.iter().filter(|sound| matches!(sound, Notification::LevelUp{..})).count();
+		assert_eq!(progress, 10);
+		assert_eq!(level_ups, 1);
+	}
+}
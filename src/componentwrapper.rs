@@ -16,11 +16,14 @@ use crate::{
 		Clan,
 		Flag,
 		Trigger,
-		Stat
+		Stat,
+		Effect
 	},
 	parameter::{Parameter},
 	fromtoparameter::FromToParameter,
 	Timestamp,
+	clock::Season,
+	controls::Direction,
 	Template,
 	Pos,
 	Result,
@@ -30,7 +33,7 @@ use crate::{
 
 
 macro_rules! components {
-	(post: $($comp: ident ($($paramname: ident : $paramtype: ty, $extraction: expr),*) $creation: expr);*;) => {
+	(post: $($comp: ident ($($paramname: ident : $paramtype: ty, [$($default: expr)?], $extraction: expr),*) $creation: expr);*;) => {
 		#[derive(Clone)]
 		pub enum ComponentWrapper{
 			$(
@@ -53,9 +56,15 @@ macro_rules! components {
 							use crate::components::$comp;
 							$(
 								let $paramname = {
-									let param = parameters
-										.remove(stringify!($paramname))
-										.ok_or(aerr!("required parameter '{}'not found", stringify!($paramname)))?;
+									#[allow(unused_mut)]
+									let mut param = parameters.remove(stringify!($paramname));
+									$(
+										if param.is_none() {
+											let default: $paramtype = $default;
+											param = Some(default.to_parameter());
+										}
+									)?
+									let param = param.ok_or(aerr!("required parameter '{}'not found", stringify!($paramname)))?;
 									<$paramtype>::from_parameter(param.clone())
 										.ok_or(aerr!("parameter {} is invalid type: {:?} is not of type {}", stringify!($paramname), param, stringify!($paramtype)))?
 								};
@@ -88,6 +97,23 @@ macro_rules! components {
 					)*
 				}
 			}
+			
+			/// Parameters with a default value can be left out
+			#[allow(unused_variables)]
+			pub fn is_optional(&self, parameter: &str) -> bool {
+				match self {
+					$(
+						Self::$comp => {
+							$(
+								if parameter == stringify!($paramname) {
+									return components!(has_default: $($default)?);
+								}
+							)*
+							false
+						},
+					)*
+				}
+			}
 		}
 		
 		use specs::{World, Entity, WorldExt};
@@ -121,17 +147,19 @@ macro_rules! components {
 		components!(pre: ($($done)* $comp () {$comp};) $($tail)*);
 	};
 	// struct is just parameters
-	(pre: ($($done: tt)*) $comp: ident ($($paramname: ident : $paramtype: ty),*);$($tail:tt)*) => {
-		components!(pre: ($($done)* $comp ($($paramname : $paramtype, {$comp.$paramname.clone()}),*) {$comp{$($paramname,)*}};) $($tail)*);
+	(pre: ($($done: tt)*) $comp: ident ($($paramname: ident : $paramtype: ty $(= $default: expr)?),*);$($tail:tt)*) => {
+		components!(pre: ($($done)* $comp ($($paramname : $paramtype, [$($default)?], {$comp.$paramname.clone()}),*) {$comp{$($paramname,)*}};) $($tail)*);
 	};
 	// full definition minus variable exraction
-	(pre: ($($done: tt)*) $comp: ident ($($paramname: ident : $paramtype: ty),*) $creation: expr; $($tail:tt)*) => {
-		components!(pre: ($($done)* $comp ($($paramname : $paramtype, {None?}),*) $creation;) $($tail)*);
+	(pre: ($($done: tt)*) $comp: ident ($($paramname: ident : $paramtype: ty $(= $default: expr)?),*) $creation: expr; $($tail:tt)*) => {
+		components!(pre: ($($done)* $comp ($($paramname : $paramtype, [$($default)?], {None?}),*) $creation;) $($tail)*);
 	};
 	// full definition
-	(pre: ($($done: tt)*) $comp: ident ($($paramname: ident : $paramtype: ty, ($extraction: expr)),*) $creation: expr; $($tail:tt)*) => {
-		components!(pre: ($($done)* $comp ($($paramname : $paramtype, $extraction),*) $creation;) $($tail)*);
+	(pre: ($($done: tt)*) $comp: ident ($($paramname: ident : $paramtype: ty $(= $default: expr)?, ($extraction: expr)),*) $creation: expr; $($tail:tt)*) => {
+		components!(pre: ($($done)* $comp ($($paramname : $paramtype, [$($default)?], $extraction),*) $creation;) $($tail)*);
 	};
+	(has_default:) => {false};
+	(has_default: $default: expr) => {true};
 	(pre: ($($done: tt)*)) => {
 		components!(post: $($done)*);
 	};
@@ -143,7 +171,7 @@ components!(all:
 	Movable (cooldown: i64);
 	Player (name: PlayerId) {Player::new(name)};
 	Item (item: ItemId) {Item(item)};
-	Inventory () {panic!("inventory from parameters not implemented")};
+	Inventory (capacity: i64) {Inventory{items: Vec::new(), capacity: capacity as usize}};
 	Health (health: i64, maxhealth: i64);
 	Serialise () {panic!("serialise from parameters not implemented")};
 	RoomExit (destination: RoomId, dest_pos: String) {
@@ -156,27 +184,31 @@ components!(all:
 				}
 		}
 	};
-	Trap (damage: i64) {Trap{attack: AttackType::Attack(damage)}};
-	Fighter (damage: i64, cooldown: i64) {Fighter{attack: AttackType::Attack(damage), cooldown, range: 1}};
+	Trap (damage: i64, effects: Vec<Effect> = Vec::new()) {Trap{attack: AttackType::Attack(damage), effects}};
+	Fighter (damage: i64, cooldown: i64, range: Option<i64> = None, effects: Vec<Effect> = Vec::new()) {Fighter{attack: AttackType::Attack(damage), cooldown, range: range.unwrap_or(1), effects}};
+	Projectile (damage: i64, direction: Direction, range: i64, effects: Vec<Effect>) {Projectile{attack: AttackType::Attack(damage), direction, range, effects, attacker: None}};
 	Healing (delay: i64, health: i64) {Healing{delay, health, next_heal: None}};
 	Autofight () {Autofight::default()};
-	MonsterAI (move_chance: f64, view_distance: i64, homesickness: f64, home: Option<Pos>);
-	Spawner (amount: i64, clan: String, template: Template, radius: i64) {
+	Pvp () {Pvp};
+	MonsterAI (move_chance: f64, view_distance: i64, homesickness: f64, home: Option<Pos>, behaviour: Option<String> = None, state: Option<String> = None, anchor: Option<Pos> = None);
+	Spawner (amount: i64, clan: String, template: Template, radius: i64, required_flags: HashSet<Flag> = HashSet::new(), season_templates: HashMap<Season, Template> = HashMap::new()) {
 		Spawner{
 			amount: amount as usize,
 			clan: Clan{name: clan},
 			template: template,
 			saturated: false,
-			radius
+			radius,
+			required_flags,
+			season_templates
 		}
 	};
 	Clan (name: String);
-	Faction (faction: String) {Faction::from_str(faction.as_str()).map_err(|_|aerr!("invalid faction name '{}'", faction))?};
+	Faction (faction: String) {Faction{name: faction}};
 	Interactable (typ: String, arg: Parameter) {
 		Interactable::parse_from_parameter(&typ, &arg).ok_or(aerr!("invalid interaction {} {:?}", typ, arg))?
 	};
 	Loot (loot: Vec<(Template, f64)>);
-	Timer (trigger: Trigger, delay: i64, spread: f64, target_time: Option<Timestamp>);
+	Timer (trigger: Trigger, delay: i64, spread: f64, target_time: Option<Timestamp>, season_factors: HashMap<Season, f64> = HashMap::new());
 	TimeOffset (dtime: i64);
 	Flags (flags: Vec<String>) {
 		Flags(
@@ -187,12 +219,15 @@ components!(all:
 		)
 	};
 	Ear () {Ear::default()};
+	StatusEffects () {StatusEffects::default()};
 	Build (obj: Template);
 	Whitelist (allowed: HashMap<String, HashSet<PlayerId>>);
-	Minable (trigger: String, total: i64) {
+	Grave (owner: PlayerId, items: Vec<ItemId>, public_at: Timestamp);
+	RespawnPoint (room: RoomId, pos: Pos);
+	Minable (trigger: String, total: i64, progress: i64 = 0) {
 		Minable {
 			trigger: Trigger::from_str(&trigger).map_err(|_|aerr!("invalid trigger name {}", trigger))?,
-			progress: 0,
+			progress,
 			total
 		}
 	};
@@ -211,7 +246,7 @@ components!(all:
 				).collect::<std::result::Result<Vec<Trigger>, std::boxed::Box<errors::AError>>>()?
 		}
 	};
-	Stats (skills: HashMap<Stat, i64>);
+	Stats (skills: HashMap<Stat, i64>, experience: HashMap<Stat, i64> = HashMap::new());
 	Requirements (required_flags: HashSet<Flag>, blocking_flags: HashSet<Flag>);
 	Description (description: String);
 );
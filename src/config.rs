@@ -19,6 +19,9 @@ pub struct Config {
 	#[structopt(short, long, env="ASCIIFARM_USER_DIR", help="The directory in which the user sign-in data lives")]
 	pub user_dir: Option<PathBuf>,
 	
+	#[structopt(long, help="Keep savegames and user registrations only in memory. Nothing is written to the save and user directories")]
+	pub ephemeral: bool,
+	
 	
 	
 	#[structopt(long, env="USER", help="The name(s) of the server admin(s)")]
@@ -27,10 +30,44 @@ pub struct Config {
 	#[structopt(long, default_value="100", help="The time (in milliseconds) between two steps")]
 	pub step_duration: u64,
 	
+	#[structopt(long, default_value="10", help="The maximum number of steps that are run without waiting to catch up after slow steps. When the server is further behind, the missed steps are skipped")]
+	pub max_catchup: u32,
+	
+	#[structopt(long, default_value="6000", help="The time (in steps) between two reports of the step duration statistics. 0 disables the reports")]
+	pub metrics_interval: i64,
+	
 	#[structopt(long, default_value="300", help="The time (in steps) between two saves")]
 	pub save_interval: i64,
 	
 	#[structopt(long, default_value="300", help="The time (in steps) between the last player leaving a room and the room getting unloaded. Unloading is only done when the rooms are saved, so it could actually take up to save_interval more steps")]
 	pub unload_age: i64,
 	
+	#[structopt(long, default_value="100", help="The maximum number of timer steps that are simulated when a room is loaded again, to catch up with the time it was unloaded. 0 disables catching up")]
+	pub fast_forward_steps: i64,
+	
+	#[structopt(subcommand)]
+	pub command: Option<Command>
+}
+
+#[derive(Debug, StructOpt)]
+pub enum Command {
+	#[structopt(about="Write the content, savegames and users of the world to a single archive file")]
+	Export {
+		#[structopt(help="The archive file to write to")]
+		archive: PathBuf
+	},
+	#[structopt(about="Load the savegames and users from an archive file into the world")]
+	Import {
+		#[structopt(help="The archive file to read from")]
+		archive: PathBuf,
+		
+		#[structopt(long, help="Replace the content directory by the content from the archive before importing. Files in the content directories that are not in the archive are removed")]
+		replace_content: bool,
+		
+		#[structopt(long, help="Import the savegames even if they are not compatible with the content")]
+		force: bool,
+		
+		#[structopt(long, help="Import into a save directory that already has savegames or users. Savegames that are not in the archive are kept")]
+		overwrite: bool
+	}
 }
@@ -5,7 +5,7 @@ use serde_json::{Value, json};
 use specs::Entity;
 use crate::{PlayerId, Pos};
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[serde(rename_all="lowercase")]
 pub enum Direction {
 	#[serde(alias="n")]
@@ -41,8 +41,13 @@ pub enum Control {
 	Use(usize),
 	Attack(Vec<Direction>),
 	AttackTarget(Entity),
+	Shoot(Direction),
 	Interact(Vec<Direction>, Option<String>),
-	Describe(Direction)
+	Describe(Direction),
+	/// Opt in to (or out of) fighting other players
+	Pvp(bool),
+	/// Leave the purgatory
+	Respawn
 }
 
 
@@ -78,6 +83,9 @@ impl Control {
 				"attack" => Some(Control::Attack(
 					parse_directions(val.get(1)?)?
 				)),
+				"shoot" => Some(Control::Shoot(
+					Direction::deserialize(val.get(1)?).ok()?
+				)),
 				"interact" => Some(Control::Interact(
 					parse_directions(val.get(1)?)?,
 					if let Some(argument) = val.get(2) {
@@ -93,6 +101,8 @@ impl Control {
 				"describe" => Some(Control::Describe(
 					Direction::deserialize(val.get(1)?).ok()?
 				)),
+				"pvp" => Some(Control::Pvp(val.get(1)?.as_bool()?)),
+				"respawn" => Some(Control::Respawn),
 				_ => None
 			}
 		} else {None}
@@ -107,6 +117,8 @@ fn parse_directions(val: &Value) -> Option<Vec<Direction>> {
 pub enum Action {
 	Join(PlayerId),
 	Leave(PlayerId),
-	Input(PlayerId, Control)
+	Input(PlayerId, Control),
+	/// Reload the content; when the flag is set also recreate the rooms without players
+	Reload(PlayerId, bool)
 }
 
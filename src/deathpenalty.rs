@@ -0,0 +1,80 @@
+
+use serde::{Serialize, Deserialize};
+use crate::{
+	ItemId,
+	components::Inventory
+};
+
+/// Which items a player loses when dying
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DeathDrop {
+	Nothing,
+	Unequipped,
+	All
+}
+
+/// What happens to the items of a player that dies
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DeathPenalty {
+	/// The items that are left in a grave at the place of death
+	pub drop: DeathDrop,
+	/// The number of steps in which only the owner can take the items from the grave
+	pub private_time: i64
+}
+
+impl Default for DeathPenalty {
+	fn default() -> Self {
+		Self {
+			drop: DeathDrop::Nothing,
+			private_time: 6000
+		}
+	}
+}
+
+impl DeathPenalty {
+
+	/// Remove the items that are lost from the inventory
+	pub fn take_items(&self, inventory: &mut Inventory) -> Vec<ItemId> {
+		let (lost, kept) = inventory.items.drain(..).partition(|entry| match self.drop {
+			DeathDrop::Nothing => false,
+			DeathDrop::Unequipped => !entry.is_equipped,
+			DeathDrop::All => true
+		});
+		inventory.items = kept;
+		lost.into_iter().map(|entry| entry.itemid).collect()
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{
+		components::inventory::InventoryEntry,
+		item::{Item, ItemAction},
+		Template
+	};
+
+	fn inventory() -> Inventory {
+		let entry = |name: &str, is_equipped| InventoryEntry{
+			itemid: ItemId(name.to_string()),
+			item: Item{ent: Template::empty(name), name: name.to_string(), action: ItemAction::None},
+			is_equipped
+		};
+		Inventory{items: vec![entry("sword", true), entry("stone", false)], capacity: 10}
+	}
+
+	#[test]
+	fn unequipped_items_are_lost() {
+		let mut inv = inventory();
+		let penalty = DeathPenalty{drop: DeathDrop::Unequipped, private_time: 10};
+		assert_eq!(penalty.take_items(&mut inv), vec![ItemId("stone".to_string())]);
+		assert_eq!(inv.items.len(), 1);
+		assert!(inv.items[0].is_equipped);
+		let mut inv = inventory();
+		assert!(DeathPenalty::default().take_items(&mut inv).is_empty());
+		assert_eq!(inv.items.len(), 2);
+	}
+}
@@ -3,7 +3,7 @@ use std::collections::HashMap;
 use serde::{de, Deserialize, Serialize, Deserializer};
 use crate::{
 	assemblage::Assemblage,
-	componentwrapper::PreEntity,
+	componentwrapper::{PreEntity, ComponentType},
 	Template,
 	template::EntityType,
 	Result as AnyResult,
@@ -12,21 +12,45 @@ use crate::{
 	item::Item,
 	item::ItemAction,
 	parameter::Parameter,
-	Sprite
+	Sprite,
+	components::faction::{FactionTable, Relation},
+	behaviour::{Behaviour, BehaviourTable}
 };
 
 #[derive(Default, Clone)]
 pub struct Encyclopedia {
 	assemblages: HashMap<EntityType, Assemblage>,
-	items: HashMap<ItemId, Item>
+	items: HashMap<ItemId, Item>,
+	/// Only the declared relations; the defaults are added in faction_table
+	factions: HashMap<String, HashMap<String, Relation>>,
+	behaviours: HashMap<String, Behaviour>
 }
 
 impl Encyclopedia {
 	
-	pub fn validate(&self) -> AnyResult<()> {
+	/// Check the assemblages, behaviours and factions on their own.
+	/// This also works for an encyclopedia that uses names from another encyclopedia it will be merged with
+	pub fn validate_structure(&self) -> AnyResult<()> {
 		for (name, assemblage) in self.assemblages.iter() {
 			assemblage.validate().map_err(|e| aerr!("invalid assemblage {}: {}", name.0, e))?;
 		}
+		for (name, behaviour) in self.behaviours.iter() {
+			behaviour.validate().map_err(|e| aerr!("invalid behaviour {}: {}", name, e))?;
+		}
+		FactionTable(self.factions.clone()).validate()?;
+		Ok(())
+	}
+	
+	/// Check the structure and whether all names that are used are declared
+	pub fn validate(&self) -> AnyResult<()> {
+		self.validate_structure()?;
+		for (name, assemblage) in self.assemblages.iter() {
+			if let Some(Parameter::String(behaviour)) = assemblage.parameter(ComponentType::MonsterAI, "behaviour") {
+				if !self.behaviours.contains_key(&behaviour) {
+					return Err(aerr!("invalid assemblage {}: unknown behaviour {}", name.0, behaviour));
+				}
+			}
+		}
 		Ok(())
 	}
 	
@@ -44,15 +68,38 @@ impl Encyclopedia {
 	pub fn merge(mut self, mut other: Encyclopedia) -> Encyclopedia {
 		self.assemblages.extend(other.assemblages.drain());
 		self.items.extend(other.items.drain());
+		for (name, relations) in other.factions.iter() {
+			for other_name in relations.keys() {
+				// the later declaration wins, also when the earlier one is in the other direction
+				if let Some(reverse) = self.factions.get_mut(other_name) {
+					reverse.remove(name);
+				}
+			}
+		}
+		for (name, relations) in other.factions.drain() {
+			self.factions.entry(name).or_default().extend(relations);
+		}
+		self.behaviours.extend(other.behaviours.drain());
 		self
 	}
+	
+	/// The default faction relations with the declared relations on top
+	pub fn faction_table(&self) -> FactionTable {
+		let mut table = FactionTable::default();
+		table.merge(FactionTable(self.factions.clone()));
+		table
+	}
+	
+	pub fn behaviour_table(&self) -> BehaviourTable {
+		BehaviourTable::new(self.behaviours.clone())
+	}
 }
 
 
 impl<'de> Deserialize<'de> for Encyclopedia {
 	fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
 	where D: Deserializer<'de> {
-		let EncyclopediaSave{mut assemblages, items, templates} = EncyclopediaSave::deserialize(deserializer)?;
+		let EncyclopediaSave{mut assemblages, items, templates, factions, behaviours} = EncyclopediaSave::deserialize(deserializer)?;
 		let mut itemdefs = HashMap::new();
 		for (id, item) in items.into_iter(){
 			let sprite = item.sprite.unwrap_or(Sprite(id.clone()));
@@ -77,7 +124,9 @@ impl<'de> Deserialize<'de> for Encyclopedia {
 		
 		Ok(Encyclopedia{
 			assemblages,
-			items: itemdefs
+			items: itemdefs,
+			factions,
+			behaviours
 		})
 	}
 }
@@ -95,5 +144,54 @@ struct EncyclopediaSave {
 	#[serde(default)]
 	items: HashMap<String, ItemSave>,
 	#[serde(default)]
-	templates: HashMap<EntityType, (EntityType, HashMap<String, Parameter>)>
+	templates: HashMap<EntityType, (EntityType, HashMap<String, Parameter>)>,
+	/// For each faction how it relates to other factions
+	#[serde(default)]
+	factions: HashMap<String, HashMap<String, Relation>>,
+	/// State machines for creatures, used by name in the MonsterAI component
+	#[serde(default)]
+	behaviours: HashMap<String, Behaviour>
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use serde_json::json;
+	
+	#[test]
+	fn unknown_behaviour_is_invalid() {
+		let encyclopedia = |behaviour: &str| Encyclopedia::deserialize(&json!({
+			"assemblages": {
+				"guard": {"sprite": "guard", "height": 1, "components": [
+					["MonsterAI", {"move_chance": 0.0, "view_distance": 3, "homesickness": 0.0, "home": [], "behaviour": behaviour}]
+				]}
+			},
+			"behaviours": {
+				"patrol": {"start": "patrol", "states": {"patrol": {"action": {"patrol": [[0, 0], [2, 0]]}}}}
+			}
+		})).unwrap();
+		assert!(encyclopedia("patrol").validate().is_ok());
+		assert!(encyclopedia("patorl").validate().is_err());
+		assert!(encyclopedia("patorl").validate_structure().is_ok());
+	}
+	
+	#[test]
+	fn behaviour_can_be_declared_in_other_encyclopedia() {
+		let creatures = Encyclopedia::deserialize(&json!({
+			"assemblages": {
+				"guard": {"sprite": "guard", "height": 1, "components": [
+					["MonsterAI", {"move_chance": 0.0, "view_distance": 3, "homesickness": 0.0, "home": [], "behaviour": "patrol"}]
+				]}
+			}
+		})).unwrap();
+		let behaviours = Encyclopedia::deserialize(&json!({
+			"behaviours": {
+				"patrol": {"start": "patrol", "states": {"patrol": {"action": {"patrol": [[0, 0], [2, 0]]}}}}
+			}
+		})).unwrap();
+		assert!(creatures.validate().is_err());
+		assert!(creatures.validate_structure().is_ok());
+		assert!(creatures.merge(behaviours).validate().is_ok());
+	}
 }
@@ -2,6 +2,8 @@
 use std::collections::{HashSet, HashMap};
 use std::hash::Hash;
 use std::str::FromStr;
+use std::convert::TryFrom;
+use serde::Deserialize;
 use crate::{
 	parameter::Parameter,
 	Template,
@@ -10,7 +12,9 @@ use crate::{
 	Sprite,
 	ItemId,
 	RoomId,
-	components::{Trigger, Stat, Faction, Flag},
+	components::{Trigger, Stat, Flag, Effect},
+	clock::Season,
+	controls::Direction,
 	Timestamp
 };
 
@@ -103,8 +107,8 @@ macro_rules! fromtostr {
 
 fromtostr!(Trigger);
 fromtostr!(Stat);
-fromtostr!(Faction);
 fromtostr!(Flag);
+fromtostr!(Season);
 
 impl<T> FromToParameter for Vec<T>
 where T: FromToParameter {
@@ -206,6 +210,24 @@ impl FromToParameter for Pos {
 	}
 }
 
+impl FromToParameter for Direction {
+	fn from_parameter(p: Parameter) -> Option<Self>{
+		Direction::deserialize(serde_json::Value::String(String::from_parameter(p)?)).ok()
+	}
+	fn to_parameter(self) -> Parameter {
+		serde_json::to_value(self).ok().and_then(|v| v.as_str().map(str::to_string)).unwrap_or_default().to_parameter()
+	}
+}
+
+impl FromToParameter for Effect {
+	fn from_parameter(p: Parameter) -> Option<Self>{
+		Effect::try_from(<(String, i64, i64)>::from_parameter(p)?).ok()
+	}
+	fn to_parameter(self) -> Parameter {
+		<(String, i64, i64)>::from(self).to_parameter()
+	}
+}
+
 impl<T> FromToParameter for Option<T>
 where T: FromToParameter {
 	fn from_parameter(p: Parameter) -> Option<Self>{
@@ -1,6 +1,6 @@
 
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::io;
 
 use serde_json::{Value, json};
@@ -12,7 +12,8 @@ use crate::{
 	controls::{Control, Action},
 	server::Server,
 	PlayerId,
-	auth::{UserRegistry, LoaderError}
+	auth::{UserRegistry, LoaderError},
+	util::strip_prefix
 };
 
 #[derive(Debug, Clone, PartialEq)]
@@ -47,7 +48,9 @@ pub struct GameServer {
 	connections: HashMap<PlayerId, (usize, usize)>,
 	users: Box<dyn UserRegistry>,
 	servers: Vec<Box<dyn Server>>,
-	admins: String
+	admins: String,
+	/// players that proved their name with a pass token or their tilde username
+	verified: HashSet<PlayerId>
 }
 
 impl GameServer {
@@ -57,7 +60,8 @@ impl GameServer {
 			connections: HashMap::new(),
 			servers,
 			users,
-			admins
+			admins,
+			verified: HashSet::new()
 		}
 	}
 	
@@ -90,6 +94,7 @@ impl GameServer {
 			for id in left {
 				if let Some(player) = self.players.remove(&(serverid, id)){
 					self.connections.remove(&player);
+					self.verified.remove(&player);
 					self.broadcast_message(&format!("{} disconnected", player));
 					actions.push(Action::Leave(player.clone()));
 				}
@@ -126,6 +131,10 @@ impl GameServer {
 		}
 	}
 	
+	pub fn send_message(&mut self, player: &PlayerId, text: &str) -> Result<(), io::Error> {
+		self.send(player, json!(["message", text, "server"]))
+	}
+	
 	pub fn send_player_error(&mut self, player: &PlayerId, errname: &str, err_text: &str) -> Result<(), io::Error> {
 		self.send(player, json!(["error", errname, err_text]))
 	}
@@ -151,13 +160,16 @@ impl GameServer {
 					return Err(merr!(action, "You can not change your name"));
 				}
 				let player = PlayerId(name);
-				self.authenticate(&player, auth.clone(), id)?;
+				let verified = self.authenticate(&player, auth.clone(), id)?;
 				if self.connections.contains_key(&player) {
 					return Err(merr!("nametaken", "Another connection to this player exists already"));
 				}
 				self.broadcast_message(&format!("{} connected", player));
 				self.players.insert(id, player.clone());
 				self.connections.insert(player.clone(), id);
+				if verified {
+					self.verified.insert(player.clone());
+				}
 				if let Err(_) = self.send(&player, json!(["connected", format!("successfully connected as {}", player)])){
 					return Err(merr!("server", "unable to send connected message"))
 				}
@@ -176,6 +188,9 @@ impl GameServer {
 			}
 			Message::Chat(text) => {
 				let player = self.players.get(&id).ok_or(merr!(action, "Set a valid name before you send any other messages"))?.clone();
+				if text.starts_with('/') {
+					return self.handle_command(&player, &text);
+				}
 				self.broadcast_message(&format!("{}: {}", player, text));
 				Ok(None)
 			}
@@ -187,21 +202,39 @@ impl GameServer {
 		}
 	}
 	
-	fn authenticate(&self, player: &PlayerId, auth: Authentication, (serverid, connectionid): (usize, usize)) -> Result<(), MessageError> {
+	fn is_admin(&self, player: &PlayerId) -> bool {
+		self.verified.contains(player) &&
+			self.admins.split(|c: char| c == ',' || c.is_whitespace()).any(|admin| admin == player.0)
+	}
+	
+	fn handle_command(&mut self, player: &PlayerId, text: &str) -> Result<Option<Action>, MessageError> {
+		let words: Vec<&str> = text.split_whitespace().collect();
+		match words.as_slice() {
+			["/reload"] | ["/reload", "rooms"] => {
+				if !self.is_admin(player) {
+					return Err(merr!("notallowed", "Only admins can reload the content"));
+				}
+				Ok(Some(Action::Reload(player.clone(), words.len() > 1)))
+			}
+			_ => Err(merr!(msg, format!("unknown command {}", text)))
+		}
+	}
+	
+	/// Returns whether the player proved to own the name
+	fn authenticate(&self, player: &PlayerId, auth: Authentication, (serverid, connectionid): (usize, usize)) -> Result<bool, MessageError> {
 		Ok(match auth {
 			Authentication::Guest => {
 				if self.users.user_exists(&player) {
 					return Err(merr!("registered", "This name is registered. Use another name or authenticate for this name"))
 				}
-				()
+				false
 			}
 			Authentication::Tilde => {
-				let (firstchar, username) = player.0.split_at(1);
-				if firstchar == "~" {
-					if Some(username.to_string()) != self.servers[serverid].get_name(connectionid) {
-						return Err(merr!(name, "A tilde name must match your username"));
-					}
+				let username = strip_prefix(&player.0, "~").ok_or(merr!(name, "A tilde name must start with '~'"))?;
+				if Some(username.to_string()) != self.servers[serverid].get_name(connectionid) {
+					return Err(merr!(name, "A tilde name must match your username"));
 				}
+				true
 			}
 			Authentication::Passtoken(token) => {
 				match self.users.load_user(player) {
@@ -213,7 +246,7 @@ impl GameServer {
 						if !user.validate_token(&token) {
 							return Err(merr!("invalidtoken", "invalid pass token"));
 						}
-						()
+						true
 					}
 					Err(LoaderError::InvalidResource(err)) => {
 						println!("failed to load user data for user '{}': {}", player, err);
@@ -287,3 +320,53 @@ fn parse_message(msg: &str) -> Result<Message, MessageError> {
 }
 
 
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::auth::MemoryRegister;
+
+	struct TestServer {
+		username: Option<String>
+	}
+
+	impl Server for TestServer {
+		fn accept_pending_connections(&mut self) -> Vec<usize> {
+			Vec::new()
+		}
+		fn recv_pending_messages(&mut self) -> (Vec<(usize, String)>, Vec<usize>) {
+			(Vec::new(), Vec::new())
+		}
+		fn send(&mut self, _id: usize, _text: &str) -> Result<(), io::Error> {
+			Ok(())
+		}
+		fn broadcast(&mut self, _text: &str) {}
+		fn get_name(&self, _id: usize) -> Option<String> {
+			self.username.clone()
+		}
+	}
+
+	fn gameserver(username: &str) -> GameServer {
+		GameServer::new(
+			vec![Box::new(TestServer{username: Some(username.to_string())})],
+			Box::new(MemoryRegister::default()),
+			"admin,~admin".to_string()
+		)
+	}
+
+	#[test]
+	fn forged_tilde_login_is_not_admin() {
+		let mut server = gameserver("mallory");
+		assert!(server.handle_message((0, 1), Message::Auth("admin".to_string(), Authentication::Tilde, true)).is_err());
+		assert!(server.handle_message((0, 1), Message::Auth("~admin".to_string(), Authentication::Tilde, true)).is_err());
+		assert!(!server.is_admin(&PlayerId("admin".to_string())));
+		assert!(server.handle_message((0, 1), Message::Auth("admin".to_string(), Authentication::Guest, true)).is_ok());
+		assert!(!server.is_admin(&PlayerId("admin".to_string())));
+	}
+
+	#[test]
+	fn tilde_login_with_matching_username_is_admin() {
+		let mut server = gameserver("admin");
+		assert!(server.handle_message((0, 1), Message::Auth("~admin".to_string(), Authentication::Tilde, true)).is_ok());
+		assert!(server.is_admin(&PlayerId("~admin".to_string())));
+	}
+}
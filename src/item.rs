@@ -7,7 +7,8 @@ use crate::{
 	Template,
 	components::{
 		Flag,
-		Equippable
+		Equippable,
+		Effect
 	}
 };
 
@@ -26,12 +27,41 @@ pub struct Item {
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum ItemAction {
-	Eat(i64),
+	Eat(Food),
 	Build(Template, HashSet<Flag>, HashSet<Flag>),
 	Equip(Equippable),
 	None
 }
 
+/// Written as just the health, or as {"health": 3, "effects": [["strength", 5, 600]]}
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(from = "FoodSave")]
+pub struct Food {
+	pub health: i64,
+	pub effects: Vec<Effect>
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+enum FoodSave {
+	Health(i64),
+	Full {
+		#[serde(default)]
+		health: i64,
+		#[serde(default)]
+		effects: Vec<Effect>
+	}
+}
+
+impl From<FoodSave> for Food {
+	fn from(save: FoodSave) -> Self {
+		match save {
+			FoodSave::Health(health) => Food{health, effects: Vec::new()},
+			FoodSave::Full{health, effects} => Food{health, effects}
+		}
+	}
+}
+
 
 #[cfg(test)]
 mod tests {
@@ -48,6 +78,17 @@ mod tests {
 		);
 	}
 	#[test]
+	fn eat_deserialise() {
+		assert_eq!(
+			ItemAction::deserialize(&json!({"eat": 3})).unwrap(),
+			ItemAction::Eat(Food{health: 3, effects: Vec::new()})
+		);
+		assert_eq!(
+			ItemAction::deserialize(&json!({"eat": {"effects": [["poison", 1, 100]]}})).unwrap(),
+			ItemAction::Eat(Food{health: 0, effects: vec![Effect::deserialize(&json!(["poison", 1, 100])).unwrap()]})
+		);
+	}
+	#[test]
 	fn invalid_stat() {
 		assert_eq!(
 			ItemAction::deserialize(&json!({"equip": {"slot": "hand", "stats": {"attack": 50}}})).ok(),
@@ -1,8 +1,7 @@
 
-use std::thread::sleep;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use std::path::PathBuf;
-use std::sync::{Arc, atomic::{AtomicBool, Ordering}};
+use std::sync::mpsc;
 use structopt::StructOpt;
 use chrono::Utc;
 
@@ -23,6 +22,7 @@ mod parameterexpression;
 mod encyclopedia;
 mod template;
 mod roomtemplate;
+mod roomgenerator;
 mod savestate;
 mod playerid;
 mod playerstate;
@@ -32,6 +32,10 @@ mod worldloader;
 mod world;
 mod sprite;
 mod timestamp;
+mod clock;
+mod deathpenalty;
+mod pathfinding;
+mod behaviour;
 mod purgatory;
 mod config;
 mod item;
@@ -39,6 +43,8 @@ mod exchange;
 mod errors;
 mod auth;
 mod fromtoparameter;
+mod archive;
+mod scheduler;
 
 use self::{
 	pos::Pos,
@@ -55,11 +61,15 @@ use self::{
 	gameserver::GameServer,
 	server::Server,
 	server::address::Address,
-	persistence::FileStorage,
+	persistence::{PersistentStorage, FileStorage, ThreadedStorage, MemoryStorage},
+	auth::{UserRegistry, FileRegister, MemoryRegister},
 	controls::Action,
 	worldloader::{WorldLoader, WorldMeta},
 	world::World,
-	worldmessages::MessageCache
+	worldmessages::MessageCache,
+	config::Command,
+	archive::WorldArchive,
+	scheduler::Scheduler
 };
 
 
@@ -68,6 +78,32 @@ fn main(){
 	
 	let config = config::Config::from_args();
 	
+	let user_dir = config.user_dir.unwrap_or(
+		FileRegister::default_register_dir().expect("couldn't find any save directory")
+	);
+	println!("user auth directory: {:?}", user_dir);
+	
+	let content_dir = config.content_dir.unwrap_or(
+		PathBuf::new()
+			.join(std::env::var("CARGO_MANIFEST_DIR").unwrap_or(".".to_string()))
+			.join("content/")
+	);
+	println!("content directory: {:?}", content_dir);
+	let loader = WorldLoader::new(content_dir);
+	
+	let save_dir = config.save_dir.unwrap_or(
+		FileStorage::default_save_dir().expect("couldn't find any save directory")
+	);
+	println!("save directory: {:?}", save_dir);
+	
+	if let Some(command) = config.command {
+		if let Err(err) = run_command(command, &loader, &FileStorage::new(save_dir), &FileRegister::new(user_dir)) {
+			println!("{}", err);
+			std::process::exit(1);
+		}
+		return;
+	}
+	
 	println!("Server admin(s): {}", config.admins);
 	
 	let adresses = config.address
@@ -88,54 +124,40 @@ fn main(){
 		.map(|a| a.to_server().unwrap())
 		.collect();
 	
-	let user_dir = config.user_dir.unwrap_or(
-		auth::FileRegister::default_register_dir().expect("couldn't find any save directory")
-	);
-	println!("user auth directory: {:?}", user_dir);
-	let users = auth::FileRegister::new(user_dir);
+	let (storage, users): (Box<dyn PersistentStorage>, Box<dyn UserRegistry>) = if config.ephemeral {
+		println!("ephemeral mode: savegames and user registrations are kept in memory only");
+		(Box::new(MemoryStorage::default()), Box::new(MemoryRegister::default()))
+	} else {
+		(Box::new(ThreadedStorage::new(Box::new(FileStorage::new(save_dir)))), Box::new(FileRegister::new(user_dir)))
+	};
 	
-	let mut gameserver = GameServer::new(servers, Box::new(users), config.admins);
+	let mut gameserver = GameServer::new(servers, users, config.admins);
 	
-	let content_dir = config.content_dir.unwrap_or(
-		PathBuf::new()
-			.join(std::env::var("CARGO_MANIFEST_DIR").unwrap_or(".".to_string()))
-			.join("content/")
-	);
-	println!("content directory: {:?}", content_dir);
-	let loader = WorldLoader::new(content_dir);
-	let WorldMeta{encyclopediae, default_room} = loader.load_world_meta().expect("Failed to load world meta information");
+	let WorldMeta{encyclopediae, default_room, clock, death_penalty} = loader.load_world_meta().expect("Failed to load world meta information");
 	
-	let mut encyclopedia = Encyclopedia::default();
-	for enc in encyclopediae {
-		encyclopedia = encyclopedia.merge(
-			loader.load_encyclopedia(&enc).expect(&format!("Failed to load encyclopedia {}", enc))
-		)
-	}
+	let encyclopedia = loader.load_encyclopediae(&encyclopediae).expect("Failed to load encyclopediae");
 	
-	let save_dir = config.save_dir.unwrap_or(
-		FileStorage::default_save_dir().expect("couldn't find any save directory")
-	);
-	println!("save directory: {:?}", save_dir);
-	let storage = FileStorage::new(save_dir);
-
-	let mut world = World::new(encyclopedia, loader, Box::new(storage), default_room);
+	let mut world = World::new(encyclopedia, loader, storage, default_room, clock);
+	world.fast_forward_steps = config.fast_forward_steps;
+	world.death_penalty = death_penalty;
 	
 	let mut message_cache = MessageCache::default();
 	
 	// close handler
-	// todo: don't let the closing wait on sleep (using a timer thread or recv_timeout)
-    let running = Arc::new(AtomicBool::new(true));
-    let r = running.clone();
+	let (stop_sender, stop) = mpsc::channel();
 	ctrlc::set_handler(move || {
 		println!("shutting down");
-		r.store(false, Ordering::SeqCst);
+		let _ = stop_sender.send(());
 	}).expect("can't set close handler");
 	
 	
 	println!("asciifarm started on {}", Utc::now());
 	
+	let mut scheduler = Scheduler::new(Duration::from_millis(config.step_duration), config.max_catchup, Instant::now());
+	scheduler.metrics.enabled = config.metrics_interval > 0;
 	
-	while running.load(Ordering::SeqCst) {
+	loop {
+		let step_start = Instant::now();
 		let actions = gameserver.update();
 		for action in actions {
 			match action {
@@ -158,6 +180,16 @@ fn main(){
 					}
 					message_cache.remove(&player);
 				}
+				Action::Reload(player, reset_rooms) => {
+					let text = match world.reload_content(reset_rooms) {
+						Ok(reset) => format!("content reloaded, {} rooms recreated", reset),
+						Err(err) => format!("failed to reload content: {}", err)
+					};
+					println!("{} (requested by {})", text, player);
+					if let Err(err) = gameserver.send_message(&player, &text) {
+						println!("Error: failed to send to {:?}: {:?}", player, err);
+					}
+				}
 			}
 		}
 		world.update();
@@ -176,15 +208,47 @@ fn main(){
 				println!("Error: failed to send to {:?}: {:?}", player, err);
 			}
 		}
+		if config.metrics_interval > 0 && world.time.0 % config.metrics_interval == 0 {
+			if let Some(report) = scheduler.metrics.report() {
+				println!("step durations: {}", report);
+			}
+		}
 		
-		sleep(Duration::from_millis(config.step_duration));
+		let wait = scheduler.finish_step(step_start, Instant::now());
+		if !scheduler.wait(wait, &stop) {
+			break;
+		}
 	}
 	println!("saving world");
-	world.save();
+	world.save_all();
+	world.flush_saves();
 	println!("world saved");
 	println!("shutting down on {}", Utc::now());
 }
 
 
-
-
+fn run_command(command: Command, loader: &WorldLoader, storage: &FileStorage, users: &FileRegister) -> Result<()> {
+	match command {
+		Command::Export{archive} => {
+			let world_archive = WorldArchive::export(loader, storage, users)?;
+			world_archive.write(&archive)?;
+			println!("exported world to {:?}: {:?}", archive, world_archive.manifest);
+		}
+		Command::Import{archive, replace_content, force, overwrite} => {
+			let world_archive = WorldArchive::read(&archive)?;
+			println!("importing world from {:?}: {:?}", archive, world_archive.manifest);
+			if replace_content {
+				world_archive.write_content(&loader.directory)?;
+			}
+			if let Err(err) = world_archive.check_compatibility(loader) {
+				if !force {
+					return Err(err);
+				}
+				println!("Warning: importing anyway: {}", err);
+			}
+			world_archive.import(storage, users, overwrite)?;
+			println!("imported world");
+		}
+	}
+	Ok(())
+}
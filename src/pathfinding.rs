@@ -0,0 +1,215 @@
+
+use std::collections::{BinaryHeap, HashMap};
+use std::cmp::Reverse;
+use crate::{
+	Pos,
+	Timestamp,
+	components::Route,
+	controls::Direction::{self, North, South, East, West}
+};
+
+/// The most tiles a single search expands before it gives up on reaching the goal
+pub const MAX_SEARCH: usize = 400;
+
+/// How many steps an entity waits before it searches again for a goal that could not be reached
+pub const RETRY_DELAY: i64 = 20;
+
+/// A* search over the 4 directions.
+/// When the goal can't be reached within max_search expanded tiles, the path leads to the searched tile closest to the goal.
+/// The goal itself is always considered passable, since it is usually occupied by whatever is chased.
+/// The returned path does not include the start and has the next step last.
+pub fn find_path(from: Pos, goal: Pos, max_search: usize, passable: impl Fn(Pos) -> bool) -> Vec<Pos> {
+	let mut open = BinaryHeap::new();
+	let mut costs: HashMap<Pos, i64> = HashMap::new();
+	let mut came_from: HashMap<Pos, Pos> = HashMap::new();
+	let mut closest = from;
+	let mut searched = 0;
+	costs.insert(from, 0);
+	open.push(Reverse((from.distance_to(goal), 0, from.x, from.y)));
+	while let Some(Reverse((_, cost, x, y))) = open.pop() {
+		let pos = Pos::new(x, y);
+		if cost > costs[&pos] {
+			// a shorter way to this tile was found after this entry was pushed
+			continue;
+		}
+		if pos.distance_to(goal) < closest.distance_to(goal) {
+			closest = pos;
+		}
+		if pos == goal || searched >= max_search {
+			break;
+		}
+		searched += 1;
+		for direction in &[North, South, East, West] {
+			let next = pos + direction.to_position();
+			if next != goal && !passable(next) {
+				continue;
+			}
+			let next_cost = cost + 1;
+			if costs.get(&next).map_or(true, |known| next_cost < *known) {
+				costs.insert(next, next_cost);
+				came_from.insert(next, pos);
+				open.push(Reverse((next_cost + next.distance_to(goal), next_cost, next.x, next.y)));
+			}
+		}
+	}
+	let mut steps = Vec::new();
+	let mut pos = closest;
+	while pos != from {
+		steps.push(pos);
+		pos = came_from[&pos];
+	}
+	steps
+}
+
+/// The direction of the next step towards the goal.
+/// The route is reused while its next step is still passable and it leads to the goal, or to a tile next to it; otherwise a new path is searched.
+/// After arriving as close as possible to a goal that can't be reached, the entity waits until the goal moves or RETRY_DELAY steps have passed.
+pub fn step_towards(route: &mut Route, from: Pos, goal: Pos, now: Timestamp, passable: impl Fn(Pos) -> bool) -> Option<Direction> {
+	while route.steps.last() == Some(&from) {
+		route.steps.pop();
+	}
+	if route.steps.is_empty() && route.goal == goal && route.retry_at.is_some_and(|retry_at| now < retry_at) {
+		return None;
+	}
+	if route.goal != goal && route.goal.distance_to(goal) <= 1 {
+		// a chased target moved one tile: repair the end of the route instead of searching again
+		if let Some(index) = route.steps.iter().position(|step| *step == goal) {
+			route.steps.drain(..index);
+		} else if route.steps.first() == Some(&route.goal) {
+			route.steps.insert(0, goal);
+		}
+		route.goal = goal;
+	}
+	let valid = route.goal == goal && route.steps.last().is_some_and(|next|
+		from.distance_to(*next) == 1 && (*next == goal || passable(*next))
+	);
+	if !valid {
+		route.goal = goal;
+		route.steps = find_path(from, goal, MAX_SEARCH, passable);
+		route.retry_at = if route.steps.first() == Some(&goal) {None} else {Some(now + RETRY_DELAY)};
+	}
+	let next = *route.steps.last()?;
+	[North, South, East, West].iter().copied().find(|direction| from + direction.to_position() == next)
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::collections::HashSet;
+
+	fn walls(rows: &[&str]) -> HashSet<Pos> {
+		let mut walls = HashSet::new();
+		for (y, row) in rows.iter().enumerate() {
+			for (x, c) in row.chars().enumerate() {
+				if c == '#' {
+					walls.insert(Pos::new(x as i64, y as i64));
+				}
+			}
+		}
+		walls
+	}
+
+	fn in_map(walls: &HashSet<Pos>) -> impl Fn(Pos) -> bool + '_ {
+		move |pos| pos.x >= 0 && pos.y >= 0 && pos.x < 7 && pos.y < 5 && !walls.contains(&pos)
+	}
+
+	#[test]
+	fn path_goes_around_walls() {
+		let walls = walls(&[
+			".......",
+			"...#...",
+			"...#...",
+			"...#...",
+			"......."
+		]);
+		let path = find_path(Pos::new(1, 2), Pos::new(5, 2), MAX_SEARCH, in_map(&walls));
+		assert_eq!(path.len(), 8);
+		assert_eq!(path[0], Pos::new(5, 2));
+		assert!(path.iter().all(|pos| !walls.contains(pos)));
+	}
+
+	#[test]
+	fn unreachable_goal_gives_path_to_closest_tile() {
+		let walls = walls(&[
+			"....#..",
+			"....#..",
+			"....#..",
+			"....#..",
+			"....#.."
+		]);
+		let path = find_path(Pos::new(0, 2), Pos::new(6, 2), MAX_SEARCH, in_map(&walls));
+		assert_eq!(path.first(), Some(&Pos::new(3, 2)));
+		assert_eq!(path.len(), 3);
+	}
+
+	#[test]
+	fn route_is_reused_until_blocked() {
+		let mut walls = walls(&[
+			".......",
+			".......",
+			"...#...",
+			".......",
+			"......."
+		]);
+		let mut route = Route::default();
+		let direction = step_towards(&mut route, Pos::new(1, 2), Pos::new(5, 2), Timestamp(0), in_map(&walls));
+		let next = route.steps.last().copied().unwrap();
+		assert_eq!(Pos::new(1, 2) + direction.unwrap().to_position(), next);
+		let remaining = route.steps.len();
+		step_towards(&mut route, next, Pos::new(5, 2), Timestamp(0), in_map(&walls));
+		assert_eq!(route.steps.len(), remaining - 1);
+		walls.insert(*route.steps.last().unwrap());
+		let direction = step_towards(&mut route, next, Pos::new(5, 2), Timestamp(0), in_map(&walls)).unwrap();
+		assert!(!walls.contains(&(next + direction.to_position())));
+	}
+
+	#[test]
+	fn unreachable_goal_is_not_searched_again_until_retry() {
+		let walls = walls(&[
+			"....#..",
+			"....#..",
+			"....#..",
+			"....#..",
+			"....#.."
+		]);
+		let searched = std::cell::Cell::new(0);
+		let passable = |pos| {
+			searched.set(searched.get() + 1);
+			in_map(&walls)(pos)
+		};
+		let mut route = Route::default();
+		let goal = Pos::new(6, 2);
+		assert!(step_towards(&mut route, Pos::new(3, 2), goal, Timestamp(0), passable).is_none());
+		assert!(searched.get() > 0);
+		searched.set(0);
+		assert!(step_towards(&mut route, Pos::new(3, 2), goal, Timestamp(RETRY_DELAY - 1), passable).is_none());
+		assert_eq!(searched.get(), 0);
+		step_towards(&mut route, Pos::new(3, 2), Pos::new(6, 3), Timestamp(RETRY_DELAY - 1), passable);
+		assert!(searched.get() > 0);
+		searched.set(0);
+		step_towards(&mut route, Pos::new(3, 2), Pos::new(6, 3), Timestamp(2 * RETRY_DELAY), passable);
+		assert!(searched.get() > 0);
+	}
+
+	#[test]
+	fn route_follows_goal_that_moves_one_tile() {
+		let walls = walls(&[
+			".......",
+			".......",
+			".......",
+			".......",
+			"......."
+		]);
+		let mut route = Route::default();
+		step_towards(&mut route, Pos::new(0, 2), Pos::new(5, 2), Timestamp(0), in_map(&walls));
+		let remaining = route.steps.len();
+		step_towards(&mut route, Pos::new(0, 2), Pos::new(6, 2), Timestamp(0), in_map(&walls));
+		assert_eq!(route.steps.len(), remaining + 1);
+		assert_eq!(route.steps[0], Pos::new(6, 2));
+		assert_eq!(route.steps[1], Pos::new(5, 2));
+		step_towards(&mut route, Pos::new(0, 2), Pos::new(5, 2), Timestamp(0), in_map(&walls));
+		assert_eq!(route.steps.len(), remaining);
+		assert_eq!(route.steps[0], Pos::new(5, 2));
+	}
+}
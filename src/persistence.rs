@@ -3,6 +3,9 @@ use std::path::{PathBuf};
 use std::fs;
 use std::env;
 use std::io::ErrorKind;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, mpsc};
+use std::thread;
 use serde_json;
 use serde_json::{Value, json};
 use crate::{
@@ -13,7 +16,7 @@ use crate::{
 	Timestamp,
 	aerr,
 	errors::AnyError,
-	util::write_file_safe
+	util::{write_file_safe, list_names_with_suffix}
 };
 
 
@@ -36,6 +39,14 @@ pub trait PersistentStorage {
 	fn save_room(&self, id: RoomId, state: SaveState) -> Result<(), AnyError>;
 	fn save_player(&self, id: PlayerId, sate: PlayerState) -> Result<(), AnyError>;
 	fn save_world_meta(&self, time: Timestamp) -> Result<(), AnyError>;
+	
+	fn list_rooms(&self) -> Result<Vec<RoomId>, AnyError>;
+	fn list_players(&self) -> Result<Vec<PlayerId>, AnyError>;
+	
+	/// Block until all saves that were made so far are written
+	fn flush(&self) -> Result<(), AnyError> {
+		Ok(())
+	}
 }
 
 
@@ -150,6 +161,286 @@ impl PersistentStorage for FileStorage {
 		write_file_safe(path, json!({"steps": time.0}).to_string())?;
 		Ok(())
 	}
+	
+	fn list_rooms(&self) -> Result<Vec<RoomId>, AnyError> {
+		Ok(list_names_with_suffix(self.directory.join("rooms"), ".save.json")?.into_iter().map(RoomId).collect())
+	}
+	
+	fn list_players(&self) -> Result<Vec<PlayerId>, AnyError> {
+		Ok(list_names_with_suffix(self.directory.join("players"), ".save.json")?.into_iter().map(PlayerId).collect())
+	}
+}
+
+
+#[derive(Default, Clone)]
+struct SavedStates {
+	rooms: HashMap<RoomId, SaveState>,
+	players: HashMap<PlayerId, PlayerState>,
+	world_meta: Option<Timestamp>
+}
+
+/// Storage that only keeps the saves in memory.
+/// Clones share the same saves.
+#[derive(Default, Clone)]
+pub struct MemoryStorage {
+	states: Arc<Mutex<SavedStates>>
+}
+
+impl PersistentStorage for MemoryStorage {
+	
+	fn load_room(&self, id: RoomId) -> Result<SaveState, LoaderError> {
+		self.states.lock().unwrap().rooms.get(&id).cloned().ok_or_else(|| LoaderError::MissingResource(aerr!("room {} not saved", id)))
+	}
+	
+	fn load_player(&self, id: PlayerId) -> Result<PlayerState, LoaderError> {
+		self.states.lock().unwrap().players.get(&id).cloned().ok_or_else(|| LoaderError::MissingResource(aerr!("player {} not saved", id)))
+	}
+	
+	fn load_world_meta(&self) -> Result<Timestamp, LoaderError> {
+		self.states.lock().unwrap().world_meta.ok_or_else(|| LoaderError::MissingResource(aerr!("world meta not saved")))
+	}
+	
+	fn save_room(&self, id: RoomId, state: SaveState) -> Result<(), AnyError> {
+		self.states.lock().unwrap().rooms.insert(id, state);
+		Ok(())
+	}
+	
+	fn save_player(&self, id: PlayerId, state: PlayerState) -> Result<(), AnyError> {
+		self.states.lock().unwrap().players.insert(id, state);
+		Ok(())
+	}
+	
+	fn save_world_meta(&self, time: Timestamp) -> Result<(), AnyError> {
+		self.states.lock().unwrap().world_meta = Some(time);
+		Ok(())
+	}
+	
+	fn list_rooms(&self) -> Result<Vec<RoomId>, AnyError> {
+		Ok(self.states.lock().unwrap().rooms.keys().cloned().collect())
+	}
+	
+	fn list_players(&self) -> Result<Vec<PlayerId>, AnyError> {
+		Ok(self.states.lock().unwrap().players.keys().cloned().collect())
+	}
+}
+
+enum WriterMessage {
+	Write,
+	/// the reply has the errors of the saves that could not be written
+	Flush(mpsc::Sender<Vec<String>>)
+}
+
+/// Storage that does the serialisation and writing of saves on a separate thread.
+/// Saves that are not written yet are kept in memory so loading always gives the latest state.
+pub struct ThreadedStorage {
+	inner: Arc<dyn PersistentStorage + Send + Sync>,
+	pending: Arc<Mutex<SavedStates>>,
+	sender: Option<mpsc::Sender<WriterMessage>>,
+	writer: Option<thread::JoinHandle<()>>
+}
+
+impl ThreadedStorage {
+	pub fn new(inner: Box<dyn PersistentStorage + Send + Sync>) -> Self {
+		let inner: Arc<dyn PersistentStorage + Send + Sync> = Arc::from(inner);
+		let pending = Arc::new(Mutex::new(SavedStates::default()));
+		let (sender, receiver) = mpsc::channel();
+		let writer = {
+			let inner = inner.clone();
+			let pending = pending.clone();
+			thread::spawn(move || {
+				for message in receiver {
+					let errors = write_pending(inner.as_ref(), &pending);
+					match message {
+						WriterMessage::Write => for err in errors {
+							println!("{}", err);
+						}
+						WriterMessage::Flush(done) => {
+							let _ = done.send(errors);
+						}
+					}
+				}
+			})
+		};
+		Self {
+			inner,
+			pending,
+			sender: Some(sender),
+			writer: Some(writer)
+		}
+	}
+	
+	fn send(&self, message: WriterMessage) -> Result<(), AnyError> {
+		self.sender
+			.as_ref()
+			.ok_or(aerr!("save writer is stopped"))?
+			.send(message)
+			.map_err(|_| aerr!("save writer thread has stopped"))?;
+		Ok(())
+	}
+}
+
+/// Write all pending saves, and return the errors for the ones that failed; those stay pending
+fn write_pending(storage: &dyn PersistentStorage, pending: &Mutex<SavedStates>) -> Vec<String> {
+	let mut errors = Vec::new();
+	let (rooms, players, world_meta) = {
+		let pending = pending.lock().unwrap();
+		(pending.rooms.clone(), pending.players.clone(), pending.world_meta)
+	};
+	// entries are only removed when they have not been replaced by a newer save in the meantime
+	for (id, state) in rooms {
+		match storage.save_room(id.clone(), state.clone()) {
+			Ok(()) => {
+				let mut pending = pending.lock().unwrap();
+				if pending.rooms.get(&id) == Some(&state) {
+					pending.rooms.remove(&id);
+				}
+			}
+			Err(err) => errors.push(format!("failed to save room {}: {}", id, err))
+		}
+	}
+	for (id, state) in players {
+		match storage.save_player(id.clone(), state.clone()) {
+			Ok(()) => {
+				let mut pending = pending.lock().unwrap();
+				if pending.players.get(&id) == Some(&state) {
+					pending.players.remove(&id);
+				}
+			}
+			Err(err) => errors.push(format!("failed to save player {}: {}", id, err))
+		}
+	}
+	if let Some(time) = world_meta {
+		match storage.save_world_meta(time) {
+			Ok(()) => {
+				let mut pending = pending.lock().unwrap();
+				if pending.world_meta == Some(time) {
+					pending.world_meta = None;
+				}
+			}
+			Err(err) => errors.push(format!("failed to save world meta: {}", err))
+		}
+	}
+	errors
+}
+
+impl PersistentStorage for ThreadedStorage {
+	
+	fn load_room(&self, id: RoomId) -> Result<SaveState, LoaderError> {
+		if let Some(state) = self.pending.lock().unwrap().rooms.get(&id) {
+			return Ok(state.clone());
+		}
+		self.inner.load_room(id)
+	}
+	
+	fn load_player(&self, id: PlayerId) -> Result<PlayerState, LoaderError> {
+		if let Some(state) = self.pending.lock().unwrap().players.get(&id) {
+			return Ok(state.clone());
+		}
+		self.inner.load_player(id)
+	}
+	
+	fn load_world_meta(&self) -> Result<Timestamp, LoaderError> {
+		if let Some(time) = self.pending.lock().unwrap().world_meta {
+			return Ok(time);
+		}
+		self.inner.load_world_meta()
+	}
+	
+	fn save_room(&self, id: RoomId, state: SaveState) -> Result<(), AnyError> {
+		self.pending.lock().unwrap().rooms.insert(id, state);
+		self.send(WriterMessage::Write)
+	}
+	
+	fn save_player(&self, id: PlayerId, state: PlayerState) -> Result<(), AnyError> {
+		self.pending.lock().unwrap().players.insert(id, state);
+		self.send(WriterMessage::Write)
+	}
+	
+	fn save_world_meta(&self, time: Timestamp) -> Result<(), AnyError> {
+		self.pending.lock().unwrap().world_meta = Some(time);
+		self.send(WriterMessage::Write)
+	}
+	
+	fn list_rooms(&self) -> Result<Vec<RoomId>, AnyError> {
+		let mut rooms = self.inner.list_rooms()?;
+		for id in self.pending.lock().unwrap().rooms.keys() {
+			if !rooms.contains(id) {
+				rooms.push(id.clone());
+			}
+		}
+		Ok(rooms)
+	}
+	
+	fn list_players(&self) -> Result<Vec<PlayerId>, AnyError> {
+		let mut players = self.inner.list_players()?;
+		for id in self.pending.lock().unwrap().players.keys() {
+			if !players.contains(id) {
+				players.push(id.clone());
+			}
+		}
+		Ok(players)
+	}
+	
+	fn flush(&self) -> Result<(), AnyError> {
+		let (done, wait) = mpsc::channel();
+		self.send(WriterMessage::Flush(done))?;
+		let errors = wait.recv().map_err(|_| aerr!("save writer thread stopped before flushing"))?;
+		if !errors.is_empty() {
+			return Err(aerr!("{}", errors.join("\n")));
+		}
+		Ok(())
+	}
 }
 
+impl Drop for ThreadedStorage {
+	fn drop(&mut self) {
+		// closing the channel stops the writer after it has written everything
+		self.sender = None;
+		if let Some(writer) = self.writer.take() {
+			if writer.join().is_err() {
+				println!("save writer thread panicked");
+			}
+		}
+	}
+}
 
+#[cfg(test)]
+mod tests {
+	use super::*;
+	
+	#[test]
+	fn flush_writes_saves() {
+		let memory = MemoryStorage::default();
+		let storage = ThreadedStorage::new(Box::new(memory.clone()));
+		let id = PlayerId("bob".to_string());
+		storage.save_player(id.clone(), PlayerState::new(id.clone())).unwrap();
+		assert!(storage.load_player(id.clone()).is_ok());
+		storage.flush().unwrap();
+		assert_eq!(memory.load_player(id.clone()).ok(), Some(PlayerState::new(id.clone())));
+	}
+	
+	#[test]
+	fn flush_returns_write_errors() {
+		// a file where the save directory should be, so nothing can be written
+		let path = std::env::temp_dir().join(format!("asciifarm-persistence-test-{}", std::process::id()));
+		fs::write(&path, "").unwrap();
+		let storage = ThreadedStorage::new(Box::new(FileStorage::new(path.clone())));
+		let id = PlayerId("bob".to_string());
+		storage.save_player(id.clone(), PlayerState::new(id.clone())).unwrap();
+		assert!(storage.flush().is_err());
+		// the save is still pending
+		assert!(storage.load_player(id).is_ok());
+		fs::remove_file(&path).unwrap();
+	}
+	
+	#[test]
+	fn drop_writes_saves() {
+		let memory = MemoryStorage::default();
+		let id = PlayerId("bob".to_string());
+		{
+			let storage = ThreadedStorage::new(Box::new(memory.clone()));
+			storage.save_player(id.clone(), PlayerState::new(id.clone())).unwrap();
+		}
+		assert!(memory.load_player(id).is_ok());
+	}
+}
@@ -7,37 +7,29 @@ use crate::{
 	RoomId,
 	ItemId,
 	components::{
-		Visible,
-		Player,
-		Inventory,
 		inventory::InventoryEntry,
-		Health,
-		Fighter,
-		Healing,
-		Movable,
-		AttackType,
-		Autofight,
-		Faction,
 		Slot,
-		Ear,
-		Stats,
 		Stat,
-		Description
+		Stats,
+		StatusEffects,
+		Pvp,
+		RespawnPoint,
+		effect::ActiveEffect
 	},
+	parameter::Parameter,
 	Result,
 	aerr,
-	Sprite,
 	Encyclopedia,
+	Template,
 	Pos,
 	hashmap
 };
 
+/// The assemblage in the encyclopedia that describes what a player is made of
 #[allow(non_upper_case_globals)]
-const maximum_health: i64 = 50;
-#[allow(non_upper_case_globals)]
-const inventory_capacity: usize = 20;
+const player_assemblage: &str = "player";
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 #[allow(dead_code)]
 pub enum RoomPos {
 	Pos(Pos),
@@ -45,13 +37,19 @@ pub enum RoomPos {
 	Unknown
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct PlayerState {
 	pub id: PlayerId,
 	pub room: Option<RoomId>,
 	pub pos: RoomPos,
 	pub inventory: Vec<(ItemId, bool)>,
-	pub health: i64
+	pub health: Option<i64>,
+	pub effects: Vec<ActiveEffect>,
+	pub experience: HashMap<Stat, i64>,
+	/// Whether the player opted in to fight other players
+	pub pvp: bool,
+	/// The room and position where the player goes after dying
+	pub respawn_point: Option<(RoomId, Pos)>
 }
 
 impl PlayerState {
@@ -62,7 +60,11 @@ impl PlayerState {
 			room: None,
 			pos: RoomPos::Unknown,
 			inventory: Vec::new(),
-			health: maximum_health/2,
+			health: None,
+			effects: Vec::new(),
+			experience: HashMap::new(),
+			pvp: false,
+			respawn_point: None
 		}
 	}
 
@@ -72,51 +74,73 @@ impl PlayerState {
 			room: Some(room),
 			pos: RoomPos::Unknown,
 			inventory,
-			health,
+			health: Some(health),
+			effects: Vec::new(),
+			experience: HashMap::new(),
+			pvp: false,
+			respawn_point: None
 		}
 	}
 	
 	pub fn respawn(&mut self) {
-		self.room = None;
-		self.pos = RoomPos::Unknown;
-		self.health = maximum_health / 2;
+		self.room = self.respawn_point.as_ref().map(|(room, _pos)| room.clone());
+		self.pos = self.respawn_point.as_ref().map(|(_room, pos)| RoomPos::Pos(*pos)).unwrap_or(RoomPos::Unknown);
+		self.health = None;
+		self.effects.clear();
 	}
 	
 	pub fn construct(&self, encyclopedia: &Encyclopedia) -> Result<PreEntity> {
-		Ok(vec![
-			ComponentWrapper::Visible(Visible{sprite: Sprite("player".to_string()), height: 1.75, name: self.id.0.clone()}),
-			ComponentWrapper::Player(Player::new(self.id.clone())),
-			ComponentWrapper::Inventory(Inventory{
-				items: self.inventory.iter().map( |(itemid, is_equipped)| {
-						let item = encyclopedia.get_item(&itemid).ok_or(aerr!("failed to load item '{:?} in inventory of player {:?}", itemid, self))?;
-					Ok(InventoryEntry{itemid: itemid.clone(), item, is_equipped: *is_equipped})
-				}).collect::<Result<Vec<InventoryEntry>>>()?,
-				capacity: inventory_capacity
-			}),
-			ComponentWrapper::Health(Health{health: self.health, maxhealth: maximum_health}),
-			ComponentWrapper::Fighter(Fighter{attack: AttackType::Attack(5), cooldown: 8, range: 1}),
-			ComponentWrapper::Healing(Healing{delay: 50, health: 1, next_heal: None}),
-			ComponentWrapper::Movable(Movable{cooldown: 2}),
-			ComponentWrapper::Autofight(Autofight::default()),
-			ComponentWrapper::Faction(Faction::Good),
-			ComponentWrapper::Ear(Ear::default()),
-			ComponentWrapper::Stats(Stats{skills: hashmap!{Stat::Gathering => 10}}),
-			ComponentWrapper::Description(Description{description: format!("a player named {}", self.id.0)})
-		])
+		let mut arguments = hashmap!{"name".to_string() => Parameter::String(self.id.0.clone())};
+		if let Some(health) = self.health {
+			arguments.insert("health".to_string(), Parameter::Int(health));
+		}
+		let mut preentity = encyclopedia.construct(&Template::new(player_assemblage, arguments).unsaved())?;
+		let items = self.inventory.iter().map( |(itemid, is_equipped)| {
+				let item = encyclopedia.get_item(itemid).ok_or(aerr!("failed to load item '{:?} in inventory of player {:?}", itemid, self))?;
+			Ok(InventoryEntry{itemid: itemid.clone(), item, is_equipped: *is_equipped})
+		}).collect::<Result<Vec<InventoryEntry>>>()?;
+		let inventory = preentity.iter_mut().find_map(|component| match component {
+			ComponentWrapper::Inventory(inventory) => Some(inventory),
+			_ => None
+		}).ok_or(aerr!("player assemblage does not have an Inventory component"))?;
+		inventory.items = items;
+		if !preentity.iter().any(|component| matches!(component, ComponentWrapper::Health(_))) {
+			return Err(aerr!("player assemblage does not have a Health component"));
+		}
+		if !self.experience.is_empty() {
+			if let Some(stats) = preentity.iter_mut().find_map(|component| match component {
+				ComponentWrapper::Stats(stats) => Some(stats),
+				_ => None
+			}) {
+				stats.experience = self.experience.clone();
+			} else {
+				preentity.push(ComponentWrapper::Stats(Stats{skills: HashMap::new(), experience: self.experience.clone()}));
+			}
+		}
+		if !self.effects.is_empty() {
+			preentity.push(ComponentWrapper::StatusEffects(StatusEffects{effects: self.effects.clone()}));
+		}
+		if self.pvp {
+			preentity.push(ComponentWrapper::Pvp(Pvp));
+		}
+		if let Some((room, pos)) = &self.respawn_point {
+			preentity.push(ComponentWrapper::RespawnPoint(RespawnPoint{room: room.clone(), pos: *pos}));
+		}
+		Ok(preentity)
 	}
 }
 
 impl Serialize for PlayerState {
 	fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
 	where S: Serializer {
-		PlayerStateSave::New{name: self.id.clone(), roomname: self.room.clone(), inventory: NewInventorySave{items: self.inventory.clone()}, health: self.health}.serialize(serializer)
+		PlayerStateSave::New{name: self.id.clone(), roomname: self.room.clone(), inventory: NewInventorySave{items: self.inventory.clone()}, health: self.health, effects: self.effects.clone(), experience: self.experience.clone(), pvp: self.pvp, respawn: self.respawn_point.clone()}.serialize(serializer)
 	}
 }
 impl<'de> Deserialize<'de> for PlayerState {
 	fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
 	where D: Deserializer<'de> {
 		Ok(match PlayerStateSave::deserialize(deserializer)? {
-			PlayerStateSave::New{name, roomname, inventory, health} => PlayerState{id: name, room: roomname, inventory: inventory.items, health, pos: RoomPos::Unknown},
+			PlayerStateSave::New{name, roomname, inventory, health, effects, experience, pvp, respawn} => PlayerState{id: name, room: roomname, inventory: inventory.items, health, effects, experience, pvp, respawn_point: respawn, pos: RoomPos::Unknown},
 			PlayerStateSave::Old{name, roomname, inventory, equipment, health} => {
 				PlayerState{
 					id: name,
@@ -132,6 +156,10 @@ impl<'de> Deserialize<'de> for PlayerState {
 						inv
 					},
 					health,
+					effects: Vec::new(),
+					experience: HashMap::new(),
+					pvp: false,
+					respawn_point: None,
 					pos: RoomPos::Unknown
 				}
 			}
@@ -154,13 +182,124 @@ enum PlayerStateSave {
 		name: PlayerId,
 		roomname: Option<RoomId>,
 		inventory: NewInventorySave,
-		health: i64
+		#[serde(default)]
+		health: Option<i64>,
+		#[serde(default, skip_serializing_if = "Vec::is_empty")]
+		effects: Vec<ActiveEffect>,
+		#[serde(default, skip_serializing_if = "HashMap::is_empty")]
+		experience: HashMap<Stat, i64>,
+		#[serde(default, skip_serializing_if = "std::ops::Not::not")]
+		pvp: bool,
+		#[serde(default, skip_serializing_if = "Option::is_none")]
+		respawn: Option<(RoomId, Pos)>
 	},
 	Old {
 		name: PlayerId,
 		roomname: Option<RoomId>,
 		inventory: OldInventorySave,
 		equipment: HashMap<Slot, ItemId>,
-		health: i64
+		#[serde(default)]
+		health: Option<i64>
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use serde_json::json;
+	
+	fn player_encyclopedia() -> Encyclopedia {
+		Encyclopedia::deserialize(&json!({
+			"assemblages": {
+				"player": {
+					"arguments": {"name": null, "health": 25},
+					"save": false,
+					"components": [
+						["Player", {"name": {"$arg": "name"}}],
+						["Inventory", {"capacity": 3}],
+						["Health", {"health": {"$arg": "health"}, "maxhealth": 50}]
+					]
+				}
+			},
+			"items": {
+				"stone": {}
+			}
+		})).unwrap()
+	}
+	
+	fn health_of(preentity: &PreEntity) -> i64 {
+		preentity.iter().find_map(|c| if let ComponentWrapper::Health(h) = c {Some(h.health)} else {None}).unwrap()
+	}
+	
+	#[test]
+	fn new_player_uses_assemblage_defaults() {
+		let preentity = PlayerState::new(PlayerId("bob".to_string())).construct(&player_encyclopedia()).unwrap();
+		assert_eq!(health_of(&preentity), 25);
+	}
+	
+	#[test]
+	fn saved_state_overrides_arguments() {
+		let state = PlayerState::create(
+			PlayerId("bob".to_string()),
+			RoomId("room".to_string()),
+			vec![(ItemId("stone".to_string()), false)],
+			12
+		);
+		let preentity = state.construct(&player_encyclopedia()).unwrap();
+		assert_eq!(health_of(&preentity), 12);
+		let inventory = preentity.iter().find_map(|c| if let ComponentWrapper::Inventory(i) = c {Some(i)} else {None}).unwrap();
+		assert_eq!(inventory.capacity, 3);
+		assert_eq!(inventory.items.len(), 1);
+	}
+	
+	#[test]
+	fn experience_is_saved() {
+		let mut state = PlayerState::new(PlayerId("bob".to_string()));
+		state.experience.insert(Stat::Mining, 100);
+		let saved = serde_json::to_value(&state).unwrap();
+		assert_eq!(saved.get("experience"), Some(&json!({"mining": 100})));
+		let loaded = PlayerState::deserialize(&saved).unwrap();
+		assert_eq!(loaded, state);
+		let preentity = loaded.construct(&player_encyclopedia()).unwrap();
+		let stats = preentity.iter().find_map(|c| if let ComponentWrapper::Stats(s) = c {Some(s)} else {None}).unwrap();
+		assert_eq!(stats.level(Stat::Mining), 2);
+		assert_eq!(Stats::total(Some(stats), None, None).get(&Stat::Mining), Some(&2));
+	}
+	
+	#[test]
+	fn pvp_is_saved() {
+		let mut state = PlayerState::new(PlayerId("bob".to_string()));
+		assert_eq!(serde_json::to_value(&state).unwrap().get("pvp"), None);
+		state.pvp = true;
+		let loaded = PlayerState::deserialize(&serde_json::to_value(&state).unwrap()).unwrap();
+		assert!(loaded.pvp);
+		assert!(loaded.construct(&player_encyclopedia()).unwrap().iter().any(|c| matches!(c, ComponentWrapper::Pvp(_))));
+	}
+	
+	#[test]
+	fn respawn_at_respawn_point() {
+		let mut state = PlayerState::create(PlayerId("bob".to_string()), RoomId("+".to_string()), Vec::new(), 0);
+		state.respawn();
+		assert_eq!((state.room.clone(), state.pos.clone(), state.health), (None, RoomPos::Unknown, None));
+		state.respawn_point = Some((RoomId("_home+bob".to_string()), Pos::new(3, 4)));
+		let mut loaded = PlayerState::deserialize(&serde_json::to_value(&state).unwrap()).unwrap();
+		assert_eq!(loaded.respawn_point, state.respawn_point);
+		loaded.respawn();
+		assert_eq!(loaded.room, Some(RoomId("_home+bob".to_string())));
+		assert_eq!(loaded.pos, RoomPos::Pos(Pos::new(3, 4)));
+	}
+	
+	#[test]
+	fn levels_grow_slower() {
+		assert_eq!(Stats::level_for(0), 0);
+		assert_eq!(Stats::level_for(24), 0);
+		assert_eq!(Stats::level_for(25), 1);
+		assert_eq!(Stats::level_for(99), 1);
+		assert_eq!(Stats::level_for(225), 3);
+	}
+	
+	#[test]
+	fn missing_player_assemblage() {
+		assert!(PlayerState::new(PlayerId("bob".to_string())).construct(&Encyclopedia::default()).is_err());
 	}
 }
@@ -37,8 +37,8 @@ pub fn create_purgatory<'a, 'b>(encyclopedia: &Encyclopedia) -> Room {
 			"      +++      ",
 			"               ",
 			"               ",
-			" RESTART CLIENT",
-			"   TO RESPAWN  "
+			"  USE RESPAWN  ",
+			"  TO PLAY AGAIN"
 		],
 		"mapping": {
 			" ": [],
@@ -70,6 +70,12 @@ impl Ground {
 		entities
 	}
 	
+	/// Whether something that walks can stand on this position
+	pub fn is_passable(&self, pos: Pos, flags: &ReadStorage<Flags>) -> bool {
+		let ground_flags = self.flags_on(pos, flags);
+		!ground_flags.contains(&Flag::Blocking) && ground_flags.contains(&Flag::Floor)
+	}
+	
 	pub fn flags_on<'a>(&self, pos: Pos, flags: &'a ReadStorage<Flags>) -> HashSet<Flag> {
 		self.components_on::<Flags>(pos, flags).into_iter().fold(HashSet::new(), |a, b| &a | &b.0)
 	}
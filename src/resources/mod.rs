@@ -17,7 +17,9 @@ use crate::{
 	RoomId,
 	playerstate::RoomPos,
 	Timestamp,
-	components::Flag
+	components::Flag,
+	controls::Direction,
+	clock::{Clock, Season}
 };
 
 
@@ -50,14 +52,35 @@ pub struct Players {
 
 #[derive(Default)]
 pub struct Emigration {
-	pub emigrants: Vec<(PlayerId, RoomId, RoomPos)>
+	pub emigrants: Vec<(PlayerId, RoomId, RoomPos)>,
+	/// Dead players that go back to their respawn point
+	pub respawns: Vec<PlayerId>
 }
 
 #[derive(Default)]
 pub struct Time {
-	pub time: Timestamp
+	pub time: Timestamp,
+	pub clock: Clock
+}
+
+impl Time {
+	pub fn is_night(&self) -> bool {
+		self.clock.is_night(self.time)
+	}
+	
+	pub fn season(&self) -> Season {
+		self.clock.season(self.time)
+	}
 }
 
 #[derive(Default, Debug, Clone, Serialize, Deserialize)]
 pub struct RoomFlags(pub HashSet<Flag>);
 
+/// The rooms that players enter when they walk off an edge of this room
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Neighbours(pub HashMap<Direction, RoomId>);
+
+/// Set by systems that change saved state without changing anything on the ground, like the items in a grave
+#[derive(Default)]
+pub struct Unsaved(pub bool);
+
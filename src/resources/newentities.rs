@@ -4,7 +4,7 @@ use crate::{
 	Encyclopedia,
 	Template,
 	Result,
-	componentwrapper::PreEntity
+	componentwrapper::{PreEntity, ComponentWrapper}
 };
 
 #[derive(Default)]
@@ -25,4 +25,11 @@ impl NewEntities {
 		self.to_build.push((pos, components));
 		Ok(())
 	}
+	/// Create an entity from a template with some extra components that can not be expressed in a template
+	pub fn create_with(&mut self, pos: Pos, template: &Template, extra: Vec<ComponentWrapper>) -> Result<()> {
+		let mut components = self.encyclopedia.construct(template)?;
+		components.extend(extra);
+		self.to_build.push((pos, components));
+		Ok(())
+	}
 }
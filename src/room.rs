@@ -1,12 +1,13 @@
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use specs::{
 	World,
 	WorldExt,
 	Join,
 	Entity,
-	RunNow
+	RunNow,
+	System
 };
 
 use crate::{
@@ -21,16 +22,30 @@ use crate::{
 		Players,
 		Emigration,
 		Time,
-		RoomFlags
+		RoomFlags,
+		Neighbours,
+		Ground,
+		Unsaved
 	},
 	components::{
+		Flag,
 		Position,
 		Serialise,
 		Player,
 		Inventory,
 		Health,
 		Removed,
-		Clan
+		Clan,
+		Stats,
+		StatusEffects,
+		Pvp,
+		RespawnPoint,
+		Healing,
+		Timer,
+		TimeOffset,
+		AttackInbox,
+		AttackMessage,
+		AttackType
 	},
 	Encyclopedia,
 	roomtemplate::RoomTemplate,
@@ -44,6 +59,8 @@ use crate::{
 	aerr,
 	Result,
 	Timestamp,
+	clock::{Clock, Season},
+	deathpenalty::DeathPenalty,
 	systems::{
 		moving::Move,
 		registernew::RegisterNew,
@@ -57,8 +74,10 @@ use crate::{
 		attacking::Attacking,
 		trapping::Trapping,
 		fight::Fight,
+		fly::Fly,
 		heal::Heal,
 		updatecooldowns::UpdateCooldowns,
+		updateeffects::UpdateEffects,
 		controlai::ControlAI,
 		die::Die,
 		spawn::Spawn,
@@ -70,22 +89,131 @@ use crate::{
 		spawntrigger::SpawnTrigger,
 		replace::Replace,
 		spawncheck::SpawnCheck,
-		describe::Describe
+		describe::Describe,
+		respawn::Respawn
 	}
 };
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum RoomType {
 	Normal,
 	Purgatory
 }
 
+/// Which systems run in a room
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct SystemConfig {
+	room_type: RoomType,
+	spawning: bool,
+	fighting: bool,
+	/// only the systems that timers trigger, for catching up with the time a room was unloaded
+	catch_up: bool
+}
+
+impl SystemConfig {
+	
+	fn new(room_type: RoomType, flags: &RoomFlags, catch_up: bool) -> Self {
+		Self {
+			room_type,
+			spawning: !flags.0.contains(&Flag::NoSpawn),
+			fighting: !flags.0.contains(&Flag::Peaceful),
+			catch_up
+		}
+	}
+	
+	fn schedule(&self) -> Schedule {
+		let mut schedule = Schedule::default();
+		match self.room_type {
+			RoomType::Normal if self.catch_up => {
+				schedule.add(Replace, "replace", &[]);
+				schedule.add(Timeout, "timeout", &[]);
+				if self.spawning {
+					schedule.add(Spawn, "spawn", &["timeout"]);
+				}
+				schedule.add(SpawnCheck, "spawn_check", &["spawn"]);
+				schedule.add(SpawnTrigger, "spawn_trigger", &["spawn"]);
+				let triggers = &["timeout", "spawn_trigger"];
+				schedule.add(Die, "die", triggers);
+				schedule.add(DropLoot, "drop_loot", triggers);
+				schedule.add(Building, "building", triggers);
+			}
+			RoomType::Normal => {
+				schedule.add(Replace, "replace", &[]);
+				schedule.add(Timeout, "timeout", &[]);
+				schedule.add(UpdateCooldowns, "update_cooldowns", &[]);
+				if self.spawning {
+					schedule.add(Spawn, "spawn", &["timeout"]);
+				}
+				schedule.add(SpawnCheck, "spawn_check", &["spawn"]);
+				schedule.add(ControlInput, "control_input", &["replace", "update_cooldowns"]);
+				schedule.add(ControlAI, "control_ai", &["replace", "update_cooldowns"]);
+				schedule.add(Take, "take", &["control_input", "control_ai"]);
+				schedule.add(Use, "use", &["control_input", "control_ai"]);
+				schedule.add(Interact, "interact", &["control_input", "control_ai", "spawn"]);
+				schedule.add(SpawnTrigger, "spawn_trigger", &["spawn"]);
+				schedule.add(Move, "move", &["take", "use", "interact"]);
+				if self.fighting {
+					schedule.add(Trapping, "trapping", &["move"]);
+					schedule.add(Fight, "fight", &["move", "trapping"]);
+					schedule.add(Fly, "fly", &["move", "trapping"]);
+				}
+				schedule.add(Heal, "heal", &["fight"]);
+				schedule.add(UpdateEffects, "update_effects", &[]);
+				schedule.add(Attacking, "attacking", &["interact", "trapping", "fight", "fly", "use", "heal", "update_effects"]);
+				let triggers = &["timeout", "interact", "spawn_trigger", "attacking"];
+				schedule.add(Die, "die", triggers);
+				schedule.add(DropLoot, "drop_loot", triggers);
+				schedule.add(Building, "building", triggers);
+				schedule.add(Migrate, "migrate", &["move", "interact", "die", "drop_loot", "building"]);
+			}
+			RoomType::Purgatory => {
+				schedule.add(UpdateCooldowns, "update_cooldowns", &[]);
+				schedule.add(ControlInput, "control_input", &["update_cooldowns"]);
+				schedule.add(Move, "move", &["control_input"]);
+				schedule.add(Respawn, "respawn", &["control_input"]);
+			}
+		}
+		schedule
+	}
+}
+
+/// Systems that are left out in some rooms
+const OPTIONAL_SYSTEMS: [&str; 4] = ["spawn", "trapping", "fight", "fly"];
+
+/// Systems in the order they were added, which must come after their dependencies.
+/// The systems run one after another because the rooms themselves are already updated in parallel.
+/// Dependencies on optional systems that are not part of this configuration are ignored
+#[derive(Default)]
+struct Schedule {
+	systems: Vec<Box<dyn for<'a> RunNow<'a>>>,
+	added: HashSet<&'static str>
+}
+
+impl Schedule {
+	fn add<S>(&mut self, system: S, name: &'static str, dependencies: &[&'static str])
+			where S: for<'c> System<'c> + 'static {
+		for dep in dependencies {
+			assert!(self.added.contains(dep) || OPTIONAL_SYSTEMS.contains(dep), "system {} depends on unknown system {}", name, dep);
+		}
+		self.systems.push(Box::new(system));
+		self.added.insert(name);
+	}
+	
+	fn run(&mut self, world: &World) {
+		for system in self.systems.iter_mut() {
+			system.run_now(world);
+		}
+	}
+}
+
 
 pub struct Room {
 	world: World,
 	pub id: RoomId,
 	places: HashMap<String, Pos>,
-	room_type: RoomType
+	room_type: RoomType,
+	last_change: Option<Timestamp>,
+	last_save: Option<Timestamp>
 }
 
 macro_rules! register_insert {
@@ -104,18 +232,24 @@ impl Room {
 
 	pub fn new(id: RoomId, encyclopedia: Encyclopedia, room_type: RoomType) -> Room {
 		let mut world = World::new();
+		world.insert(encyclopedia.faction_table());
+		world.insert(encyclopedia.behaviour_table());
+		world.insert(DeathPenalty::default());
+		world.insert(id.clone());
 		world.insert(NewEntities::new(encyclopedia));
 		register_insert!(
 			world,
-			(Position, Visible, Controller, Movable, New, Removed, Moved, Player, Inventory, Health, Serialise, RoomExit, Entered, TriggerBox, Trap, Fighter, Healing, ControlCooldown, Autofight, MonsterAI, AttackInbox, Item, Spawner, Clan, Faction, Interactable, Loot, Timer, TimeOffset, Flags, Ear, Build, Whitelist, Minable, LootHolder, OnSpawn, Substitute, Stats, Requirements, Description),
-			(Ground, Input, Output, Size, Spawn, Players, Emigration, Time, RoomFlags)
+			(Position, Visible, Controller, Movable, New, Removed, Moved, Player, Inventory, Health, Serialise, RoomExit, Entered, TriggerBox, Trap, Fighter, Projectile, Healing, ControlCooldown, Autofight, Pvp, MonsterAI, BehaviourState, Route, AttackInbox, Item, Spawner, Clan, Faction, Interactable, Loot, Timer, TimeOffset, Flags, Ear, Build, Whitelist, Grave, RespawnPoint, Minable, LootHolder, OnSpawn, Substitute, Stats, Requirements, Description, StatusEffects),
+			(Ground, Input, Output, Size, Spawn, Players, Emigration, Time, RoomFlags, Neighbours, Unsaved)
 		);
 		
 		Room {
 			world,
 			id,
 			places: HashMap::new(),
-			room_type
+			room_type,
+			last_change: None,
+			last_save: None
 		}
 	}
 	
@@ -127,6 +261,7 @@ impl Room {
 		
 		self.world.fetch_mut::<SpawnPosition>().pos = template.spawn;
 		self.world.insert::<RoomFlags>(template.flags.clone());
+		self.world.insert::<Neighbours>(template.neighbours.clone());
 		
 		for (idx, templates) in template.field.iter().enumerate() {
 			let x = (idx as i64) % width;
@@ -142,47 +277,55 @@ impl Room {
 		Ok(())
 	}
 	
+	pub fn set_clock(&mut self, clock: Clock) {
+		self.world.fetch_mut::<Time>().clock = clock;
+	}
+	
+	pub fn set_death_penalty(&mut self, death_penalty: DeathPenalty) {
+		self.world.insert(death_penalty);
+	}
+	
+	pub fn set_encyclopedia(&mut self, encyclopedia: Encyclopedia) {
+		self.world.insert(encyclopedia.faction_table());
+		self.world.insert(encyclopedia.behaviour_table());
+		self.world.fetch_mut::<NewEntities>().encyclopedia = encyclopedia;
+	}
+	
 	pub fn view(&self) -> HashMap<PlayerId, WorldMessage> {
 		self.world.fetch::<Output>().output.clone()
 	}
 	
 	pub fn update(&mut self, timestamp: Timestamp) {
+		self.step(timestamp, false);
+	}
+	
+	fn step(&mut self, timestamp: Timestamp, catch_up: bool) {
 		self.world.fetch_mut::<Time>().time = timestamp;
-		match self.room_type {
-			RoomType::Normal => {
-				Replace.run_now(&self.world);
-				Timeout.run_now(&self.world);
-				UpdateCooldowns.run_now(&self.world);
-				Spawn.run_now(&self.world);
-				SpawnCheck.run_now(&self.world);
-				ControlInput.run_now(&self.world);
-				ControlAI.run_now(&self.world);
-				Take.run_now(&self.world);
-				Use.run_now(&self.world);
-				Interact.run_now(&self.world);
-				SpawnTrigger.run_now(&self.world);
-				Move.run_now(&self.world);
-				Trapping.run_now(&self.world);
-				Fight.run_now(&self.world);
-				Heal.run_now(&self.world);
-				Attacking.run_now(&self.world);
-				Die.run_now(&self.world);
-				DropLoot.run_now(&self.world);
-				Building.run_now(&self.world);
-				Migrate.run_now(&self.world);
-			}
-			RoomType::Purgatory => {
-				UpdateCooldowns.run_now(&self.world);
-				ControlInput.run_now(&self.world);
-				Move.run_now(&self.world);
+		let (night, season) = {
+			let time = self.world.fetch::<Time>();
+			(time.is_night(), time.season())
+		};
+		{
+			let flags = &mut self.world.fetch_mut::<RoomFlags>().0;
+			flags.remove(if night {&Flag::Day} else {&Flag::Night});
+			flags.insert(if night {Flag::Night} else {Flag::Day});
+			for other in Season::ALL.iter() {
+				flags.remove(&other.flag());
 			}
+			flags.insert(season.flag());
 		}
+		let config = SystemConfig::new(self.room_type, &self.world.fetch::<RoomFlags>(), catch_up);
+		config.schedule().run(&self.world);
+		// bookkeeping that has to happen in this order at the end of every step
 		Create.run_now(&self.world);
 		Remove.run_now(&self.world);
 		self.world.maintain();
 		RegisterNew.run_now(&self.world);
 		Describe.run_now(&self.world);
 		View.run_now(&self.world);
+		if !self.world.fetch::<Ground>().changes.is_empty() || self.world.fetch::<Unsaved>().0 {
+			self.last_change = Some(timestamp);
+		}
 		Clear.run_now(&self.world);
 	}
 	
@@ -195,7 +338,7 @@ impl Room {
 		let pre_player = state.construct(&self.world.fetch::<NewEntities>().encyclopedia)?;
 		let spawn = match &state.pos {
 			RoomPos::Unknown => self.world.fetch::<SpawnPosition>().pos,
-			RoomPos::Pos(pos) => *pos,
+			RoomPos::Pos(pos) => self.resolve_pos(*pos),
 			RoomPos::Name(name) => *self.places.get(name).unwrap()
 		};
 		self.world.fetch_mut::<NewEntities>().to_build.push((spawn, pre_player));
@@ -216,6 +359,7 @@ impl Room {
 		let serialisers = self.world.read_component::<Serialise>();
 		let clans = self.world.read_component::<Clan>();
 		let mut state = SaveState::new();
+		state.time = Some(self.get_time());
 		for (entity, pos, serialiser, clan) in (&entities, &positions, &serialisers, (&clans).maybe()).join() {
 			let mut template = serialiser.template.clone();
 			for (argument, component, member) in &serialiser.extract {
@@ -231,6 +375,20 @@ impl Room {
 		state
 	}
 	
+	/// Whether anything changed since the last save.
+	/// Changes in the same step as the save still count, because new entities can settle their state (like timers) in the step after their creation.
+	pub fn has_unsaved_changes(&self) -> bool {
+		match (self.last_change, self.last_save) {
+			(Some(change), Some(save)) => change >= save,
+			(Some(_), None) => true,
+			(None, _) => false
+		}
+	}
+	
+	pub fn mark_saved(&mut self) {
+		self.last_save = Some(self.get_time());
+	}
+	
 	pub fn load_saved(&mut self, state: &SaveState) {
 		for (pos, templates) in state.changes.iter() {
 			for template in templates {
@@ -239,6 +397,65 @@ impl Room {
 		}
 	}
 	
+	/// Catch up with the time from `from` to `to` for a room that was not loaded in that time.
+	/// Healing and health effects are applied at once.
+	/// Overdue timers fire in repeated steps that only run the systems that timers trigger, until no timer is overdue or max_steps steps have run.
+	/// With max_steps 0 the room is not fast-forwarded at all.
+	pub fn fast_forward(&mut self, from: Timestamp, to: Timestamp, max_steps: i64) {
+		if to <= from || max_steps <= 0 {
+			return;
+		}
+		// loaded entities are only created at the end of the first step, so their timers start in the second
+		self.step(from, true);
+		self.step(from, true);
+		self.catch_up_health(from, to);
+		for _ in 0..max_steps {
+			if !self.has_overdue_timers(to) {
+				break;
+			}
+			self.step(to, true);
+		}
+		self.update(to);
+	}
+	
+	fn catch_up_health(&mut self, from: Timestamp, to: Timestamp) {
+		let entities = self.world.entities();
+		let mut healths = self.world.write_component::<Health>();
+		let mut healings = self.world.write_component::<Healing>();
+		for (health, healing) in (&mut healths, &mut healings).join() {
+			if health.health >= health.maxhealth || healing.delay <= 0 {
+				continue;
+			}
+			let first = healing.next_heal.unwrap_or(from + healing.delay);
+			if first <= to {
+				health.heal(healing.health * (1 + (to - first) / healing.delay));
+				healing.next_heal = None;
+			}
+		}
+		let statuses = self.world.read_component::<StatusEffects>();
+		let mut inboxes = self.world.write_component::<AttackInbox>();
+		for (entity, status) in (&entities, &statuses).join() {
+			let (poison, regeneration) = status.health_change_between(from, to);
+			if poison > 0 {
+				AttackInbox::add_message(&mut inboxes, entity, AttackMessage{typ: AttackType::Poison(poison), attacker: None});
+			}
+			if regeneration > 0 {
+				AttackInbox::add_message(&mut inboxes, entity, AttackMessage{typ: AttackType::Heal(regeneration), attacker: None});
+			}
+		}
+	}
+	
+	fn has_overdue_timers(&self, now: Timestamp) -> bool {
+		let entities = self.world.entities();
+		let timers = self.world.read_component::<Timer>();
+		let offsets = self.world.read_component::<TimeOffset>();
+		(&entities, &timers).join().any(|(entity, timer)| match timer.target_time {
+			Some(target_time) => target_time <= now,
+			// a timer that was triggered in the past starts again from that time
+			None => offsets.contains(entity)
+		})
+	}
+	
 	pub fn has_players(&self) -> bool {
 		!self.world.read_component::<Player>().is_empty()
 	}
@@ -260,12 +477,35 @@ impl Room {
 		let inventory = inventories.get(ent)?;
 		let healths = self.world.read_component::<Health>();
 		let health = healths.get(ent)?;
-		Some(PlayerState::create(
+		let mut state = PlayerState::create(
 			player.id.clone(),
 			self.id.clone(),
 			inventory.items.iter().map(|entry| (entry.itemid.clone(), entry.is_equipped)).collect(),
 			health.health,
-		))
+		);
+		if let Some(status) = self.world.read_component::<StatusEffects>().get(ent) {
+			state.effects = status.effects.clone();
+		}
+		if let Some(stats) = self.world.read_component::<Stats>().get(ent) {
+			state.experience = stats.experience.clone();
+		}
+		state.pvp = self.world.read_component::<Pvp>().contains(ent);
+		if let Some(point) = self.world.read_component::<RespawnPoint>().get(ent) {
+			state.respawn_point = Some((point.room.clone(), point.pos));
+		}
+		Some(state)
+	}
+	
+	/// Negative coordinates count from the right or bottom edge, and positions outside the room are moved to the nearest edge
+	/// Whether a player can stand on the tile at this position (which is resolved like the position of an added player)
+	pub fn can_enter(&self, pos: Pos) -> bool {
+		self.world.fetch::<Ground>().is_passable(self.resolve_pos(pos), &self.world.read_storage())
+	}
+	
+	fn resolve_pos(&self, pos: Pos) -> Pos {
+		let size = self.world.fetch::<Size>();
+		let resolve = |coord: i64, max: i64| if coord < 0 {coord + max} else {coord}.max(0).min(max - 1);
+		Pos::new(resolve(pos.x, size.width), resolve(pos.y, size.height))
 	}
 	
 	fn create_entity(&mut self, template: Template, pos: Pos) -> Result<()>{
@@ -273,10 +513,10 @@ impl Room {
 		Ok(())
 	}
 	
-	pub fn emigrate(&mut self) -> Vec<(PlayerId, RoomId, RoomPos)> {
-		let emigrants = self.world.remove::<Emigration>().expect("World does not have Emigration resource").emigrants;
+	pub fn emigrate(&mut self) -> Emigration {
+		let emigration = self.world.remove::<Emigration>().expect("World does not have Emigration resource");
 		self.world.insert(Emigration::default());
-		emigrants
+		emigration
 	}
 	
 	pub fn get_time(&self) -> Timestamp {
@@ -287,9 +527,552 @@ impl Room {
 
 #[cfg(test)]
 mod tests {
-
-
-
+	use super::*;
+	use serde::Deserialize;
+	use serde_json::json;
+	use crate::{
+		components::{Effect, Grave, MonsterAI, Visible, effect::EffectKind},
+		controls::Direction,
+		deathpenalty::DeathDrop,
+		parameter::Parameter,
+		ItemId
+	};
+	
+	fn test_room() -> Room {
+		let encyclopedia = Encyclopedia::deserialize(&json!({
+			"assemblages": {
+				"grass": {"sprite": "grass", "height": 0.1, "flags": ["Floor"]}
+			},
+			"items": {
+				"stone": {}
+			}
+		})).unwrap();
+		let mut room = Room::new(RoomId("test".to_string()), encyclopedia, RoomType::Normal);
+		room.load_from_template(&RoomTemplate::deserialize(&json!({
+			"width": 2,
+			"height": 1,
+			"spawn": [0, 0],
+			"field": [",,"],
+			"mapping": {",": "grass"}
+		})).unwrap()).unwrap();
+		room
+	}
+	
+	#[test]
+	fn unchanged_room_is_not_saved_again() {
+		let mut room = test_room();
+		assert!(!room.has_unsaved_changes());
+		room.update(Timestamp(1));
+		assert!(room.has_unsaved_changes());
+		room.mark_saved();
+		room.update(Timestamp(2));
+		// changes in the step of the save are saved once more
+		assert!(room.has_unsaved_changes());
+		room.mark_saved();
+		room.update(Timestamp(3));
+		assert!(!room.has_unsaved_changes());
+	}
+	
+	#[test]
+	fn dropped_item_makes_room_unsaved() {
+		let mut room = test_room();
+		room.update(Timestamp(1));
+		room.update(Timestamp(2));
+		room.mark_saved();
+		room.update(Timestamp(3));
+		assert!(!room.has_unsaved_changes());
+		room.create_entity(Template::empty("stone"), Pos::new(1, 0)).unwrap();
+		room.update(Timestamp(4));
+		assert!(room.has_unsaved_changes());
+		assert_eq!(room.save().changes.len(), 1);
+	}
+	
+	#[test]
+	fn damaged_wall_makes_room_unsaved() {
+		let encyclopedia = Encyclopedia::deserialize(&json!({
+			"assemblages": {
+				"wall": {
+					"arguments": {"health": 10},
+					"sprite": "wall",
+					"height": 2,
+					"components": [["Health", {"health": {"$arg": "health"}, "maxhealth": 10}]],
+					"extract": {"health": ["Health", "health"]}
+				}
+			}
+		})).unwrap();
+		let mut room = Room::new(RoomId("test".to_string()), encyclopedia, RoomType::Normal);
+		room.create_entity(Template::empty("wall"), Pos::new(0, 0)).unwrap();
+		room.update(Timestamp(1));
+		room.update(Timestamp(2));
+		room.mark_saved();
+		room.update(Timestamp(3));
+		assert!(!room.has_unsaved_changes());
+		let wall = room.world.fetch::<Ground>().cells.get(&Pos::new(0, 0)).unwrap().iter().next().copied().unwrap();
+		AttackInbox::add_message(&mut room.world.write_component::<AttackInbox>(), wall, AttackMessage{typ: AttackType::Poison(3), attacker: None});
+		room.update(Timestamp(4));
+		assert!(room.has_unsaved_changes());
+		let saved = room.save();
+		assert_eq!(saved.changes[&Pos::new(0, 0)][0].kwargs.get("health"), Some(&Parameter::Int(7)));
+	}
+	
+	/// Run a room with spikes on every tile and the given creatures for some steps
+	fn flagged_room(flags: &[&str], creatures: &[(&str, i64)]) -> Room {
+		let encyclopedia = Encyclopedia::deserialize(&json!({
+			"assemblages": {
+				"grass": {"sprite": "grass", "height": 0.1, "flags": ["Floor"]},
+				"spikes": {"sprite": "spikes", "height": 0.2, "components": [["Trap", {"damage": 100}]]},
+				"arrow": {"sprite": "arrow", "height": 0.5, "save": false},
+				"wound": {"sprite": "wound", "height": 0.25, "save": false},
+				"walker": {"sprite": "walker", "height": 1, "components": [
+					["MonsterAI", {"move_chance": 1.0, "view_distance": 0, "homesickness": 0.0, "home": []}],
+					["Movable", {"cooldown": 1}],
+					["Health", {"health": 100000, "maxhealth": 100000}]
+				]},
+				"archer": {"sprite": "archer", "height": 1, "components": [
+					["MonsterAI", {"move_chance": 0.0, "view_distance": 8, "homesickness": 0.0, "home": []}],
+					["Fighter", {"damage": 100, "cooldown": 1, "range": 5}],
+					["Faction", {"faction": "evil"}]
+				]},
+				"dummy": {"sprite": "dummy", "height": 1, "components": [
+					["Health", {"health": 100000, "maxhealth": 100000}],
+					["Faction", {"faction": "good"}]
+				]},
+				"bat": {"sprite": "bat", "height": 1},
+				"batspawner": {"components": [
+					["Timer", {"delay": 1, "spread": 0.0, "trigger": "spawn", "target_time": 0}],
+					["Spawner", {"template": {"$template": "bat"}, "amount": 1, "clan": "bats", "radius": 0}]
+				]}
+			}
+		})).unwrap();
+		let mut room = Room::new(RoomId("test".to_string()), encyclopedia, RoomType::Normal);
+		room.load_from_template(&RoomTemplate::deserialize(&json!({
+			"width": 4,
+			"height": 1,
+			"spawn": [0, 0],
+			"field": [",,,,"],
+			"mapping": {",": ["grass", "spikes"]},
+			"flags": flags
+		})).unwrap()).unwrap();
+		for (name, x) in creatures {
+			room.create_entity(Template::empty(name), Pos::new(*x, 0)).unwrap();
+		}
+		for time in 1..40 {
+			room.update(Timestamp(time));
+		}
+		room
+	}
+	
+	fn is_hurt(room: &Room) -> bool {
+		(&room.world.read_component::<Health>()).join().any(|health| health.health < health.maxhealth)
+	}
+	
+	fn has_spawned(room: &Room) -> bool {
+		!room.world.read_component::<Clan>().is_empty()
+	}
+	
+	#[test]
+	fn optional_systems_can_be_disabled() {
+		assert!(is_hurt(&flagged_room(&[], &[("walker", 1)])));
+		assert!(!is_hurt(&flagged_room(&["Peaceful"], &[("walker", 1)])));
+		assert!(is_hurt(&flagged_room(&["NoSpawn"], &[("archer", 0), ("dummy", 3)])));
+		assert!(!is_hurt(&flagged_room(&["Peaceful"], &[("archer", 0), ("dummy", 3)])));
+		assert!(has_spawned(&flagged_room(&["Peaceful"], &[("batspawner", 0)])));
+		assert!(!has_spawned(&flagged_room(&["NoSpawn"], &[("batspawner", 0)])));
+		assert!(!has_spawned(&flagged_room(&["Peaceful", "NoSpawn"], &[("batspawner", 0)])));
+	}
+	
+	#[test]
+	fn fast_forward_heals() {
+		let encyclopedia = Encyclopedia::deserialize(&json!({
+			"assemblages": {
+				"rabbit": {
+					"sprite": "rabbit",
+					"height": 1,
+					"components": [
+						["Health", {"health": 1, "maxhealth": 10}],
+						["Healing", {"delay": 5, "health": 1}]
+					]
+				}
+			}
+		})).unwrap();
+		let mut room = Room::new(RoomId("test".to_string()), encyclopedia, RoomType::Normal);
+		room.create_entity(Template::empty("rabbit"), Pos::new(0, 0)).unwrap();
+		room.fast_forward(Timestamp(100), Timestamp(1100), 100);
+		assert_eq!(room.get_time(), Timestamp(1100));
+		let healths = room.world.read_component::<Health>();
+		assert_eq!((&healths).join().next().unwrap().health, 10);
+	}
+	
+	#[test]
+	fn fast_forward_without_elapsed_time_does_nothing() {
+		let mut room = test_room();
+		room.update(Timestamp(50));
+		room.fast_forward(Timestamp(100), Timestamp(100), 100);
+		room.fast_forward(Timestamp(100), Timestamp(90), 100);
+		room.fast_forward(Timestamp(100), Timestamp(200), 0);
+		assert_eq!(room.get_time(), Timestamp(50));
+	}
+	
+	#[test]
+	fn fast_forward_fires_timers_in_order() {
+		let encyclopedia = Encyclopedia::deserialize(&json!({
+			"assemblages": {
+				"seed": {"sprite": "seed", "height": 0.1, "components": [
+					["Timer", {"delay": 10, "spread": 0.0, "trigger": "change", "target_time": []}],
+					["Build", {"obj": {"$template": "plant"}}]
+				]},
+				"plant": {"sprite": "plant", "height": 0.5, "components": [
+					["Timer", {"delay": 10, "spread": 0.0, "trigger": "change", "target_time": []}],
+					["Build", {"obj": {"$template": "tree"}}]
+				]},
+				"tree": {"sprite": "tree", "height": 2}
+			}
+		})).unwrap();
+		let sprites = |room: &Room| -> Vec<String> {
+			let visibles = room.world.read_component::<Visible>();
+			(&visibles).join().map(|visible| visible.sprite.0.clone()).collect()
+		};
+		let mut room = Room::new(RoomId("test".to_string()), encyclopedia.clone(), RoomType::Normal);
+		room.create_entity(Template::empty("seed"), Pos::new(0, 0)).unwrap();
+		room.fast_forward(Timestamp(0), Timestamp(15), 100);
+		assert_eq!(sprites(&room), vec!["plant"]);
+		
+		let mut room = Room::new(RoomId("test".to_string()), encyclopedia, RoomType::Normal);
+		room.create_entity(Template::empty("seed"), Pos::new(0, 0)).unwrap();
+		room.fast_forward(Timestamp(0), Timestamp(1000), 100);
+		assert_eq!(sprites(&room), vec!["tree"]);
+	}
+	
+	#[test]
+	fn positions_from_other_edge() {
+		let room = test_room();
+		assert_eq!(room.resolve_pos(Pos::new(-1, 0)), Pos::new(1, 0));
+		assert_eq!(room.resolve_pos(Pos::new(0, -1)), Pos::new(0, 0));
+		assert_eq!(room.resolve_pos(Pos::new(5, 3)), Pos::new(1, 0));
+	}
+	
+	#[test]
+	fn night_spawner_waits_for_night() {
+		let encyclopedia = Encyclopedia::deserialize(&json!({
+			"assemblages": {
+				"bat": {"sprite": "bat", "height": 1},
+				"batspawner": {
+					"components": [
+						["Timer", {"delay": 1, "spread": 0.0, "trigger": "spawn", "target_time": 0}],
+						["Spawner", {"template": {"$template": "bat"}, "amount": 1, "clan": "bats", "radius": 0, "required_flags": ["Night"]}]
+					]
+				}
+			}
+		})).unwrap();
+		let mut room = Room::new(RoomId("test".to_string()), encyclopedia, RoomType::Normal);
+		room.set_clock(Clock{day_length: 100, dawn: 0.25, dusk: 0.75, season_length: 7});
+		room.create_entity(Template::empty("batspawner"), Pos::new(0, 0)).unwrap();
+		for time in 40..50 {
+			room.update(Timestamp(time));
+		}
+		assert!(room.world.read_component::<Clan>().is_empty());
+		for time in 80..85 {
+			room.update(Timestamp(time));
+		}
+		assert!(!room.world.read_component::<Clan>().is_empty());
+	}
+	
+	#[test]
+	fn season_factor_stretches_timer() {
+		let encyclopedia = Encyclopedia::deserialize(&json!({
+			"assemblages": {
+				"sprout": {"sprite": "sprout", "height": 0.5, "components": [
+					["Timer", {"delay": 10, "spread": 0.0, "trigger": "remove", "target_time": [], "season_factors": [["Winter", 3.0]]}]
+				]}
+			}
+		})).unwrap();
+		let mut room = Room::new(RoomId("test".to_string()), encyclopedia, RoomType::Normal);
+		room.set_clock(Clock{day_length: 10, dawn: 0.25, dusk: 0.75, season_length: 1});
+		room.create_entity(Template::empty("sprout"), Pos::new(0, 0)).unwrap();
+		// every season lasts 10 steps, so winter is from 30 to 40
+		for time in 30..55 {
+			room.update(Timestamp(time));
+		}
+		assert!(!room.world.read_component::<Visible>().is_empty());
+		for time in 55..65 {
+			room.update(Timestamp(time));
+		}
+		assert!(room.world.read_component::<Visible>().is_empty());
+	}
+	
+	#[test]
+	fn season_template_replaces_spawned_template() {
+		let encyclopedia = Encyclopedia::deserialize(&json!({
+			"assemblages": {
+				"bat": {"sprite": "bat", "height": 1},
+				"snowbat": {"sprite": "snowbat", "height": 1},
+				"batspawner": {
+					"components": [
+						["Timer", {"delay": 1, "spread": 0.0, "trigger": "spawn", "target_time": 0}],
+						["Spawner", {"template": {"$template": "bat"}, "amount": 1, "clan": "bats", "radius": 0, "season_templates": [["Winter", {"$template": "snowbat"}]]}]
+					]
+				}
+			}
+		})).unwrap();
+		let mut room = Room::new(RoomId("test".to_string()), encyclopedia, RoomType::Normal);
+		room.set_clock(Clock{day_length: 10, dawn: 0.25, dusk: 0.75, season_length: 1});
+		room.create_entity(Template::empty("batspawner"), Pos::new(0, 0)).unwrap();
+		for time in 30..35 {
+			room.update(Timestamp(time));
+		}
+		let visibles = room.world.read_component::<Visible>();
+		let sprites: Vec<&str> = (&visibles).join().map(|visible| visible.sprite.0.as_str()).collect();
+		assert_eq!(sprites, vec!["snowbat"]);
+	}
+	
+	/// A room with the given field, where ',' is grass and '#' is a wall.
+	/// The encyclopedia has grass, wall, wound, arrow and player assemblages, and everything in `extra`
+	fn field_room(extra: serde_json::Value, field: &[&str]) -> Room {
+		let mut encyclopedia = json!({
+			"assemblages": {
+				"grass": {"sprite": "grass", "height": 0.1, "flags": ["Floor"]},
+				"wall": {"sprite": "wall", "height": 2, "flags": ["Blocking"]},
+				"wound": {"sprite": "wound", "height": 0.25, "save": false},
+				"arrow": {"sprite": "arrow", "height": 0.5, "save": false},
+				"player": {
+					"arguments": {"name": null, "health": 10},
+					"save": false,
+					"components": [
+						["Player", {"name": {"$arg": "name"}}],
+						["Inventory", {"capacity": 3}],
+						["Health", {"health": {"$arg": "health"}, "maxhealth": 10}],
+						["Fighter", {"damage": 1000, "cooldown": 1}],
+						["Faction", {"faction": "good"}]
+					]
+				}
+			}
+		});
+		for (key, value) in extra.as_object().unwrap() {
+			let part = encyclopedia.as_object_mut().unwrap().entry(key.clone()).or_insert_with(|| json!({}));
+			part.as_object_mut().unwrap().extend(value.as_object().unwrap().clone());
+		}
+		let mut room = Room::new(RoomId("test".to_string()), Encyclopedia::deserialize(&encyclopedia).unwrap(), RoomType::Normal);
+		room.load_from_template(&RoomTemplate::deserialize(&json!({
+			"width": field[0].len(),
+			"height": field.len(),
+			"spawn": [0, 0],
+			"field": field,
+			"mapping": {",": "grass", "#": ["grass", "wall"]}
+		})).unwrap()).unwrap();
+		room
+	}
+	
+	fn add_player_at(room: &mut Room, mut state: PlayerState, x: i64) {
+		state.pos = RoomPos::Pos(Pos::new(x, 0));
+		room.add_player(&state).unwrap();
+	}
+	
+	#[test]
+	fn archer_shoots_until_wall() {
+		let mut room = field_room(json!({"assemblages": {
+			"archer": {"sprite": "archer", "height": 1, "components": [
+				["MonsterAI", {"move_chance": 0.0, "view_distance": 8, "homesickness": 0.0, "home": []}],
+				["Fighter", {"damage": 1000, "cooldown": 1, "range": 5}],
+				["Faction", {"faction": "evil"}]
+			]},
+			"dummy": {"sprite": "dummy", "height": 1, "components": [
+				["Health", {"health": 10, "maxhealth": 10}],
+				["Faction", {"faction": "good"}]
+			]}
+		}}), &[",,,,,,,", ",,#,,,,"]);
+		room.create_entity(Template::empty("archer"), Pos::new(0, 0)).unwrap();
+		room.create_entity(Template::empty("archer"), Pos::new(0, 1)).unwrap();
+		room.create_entity(Template::empty("dummy"), Pos::new(4, 0)).unwrap();
+		room.create_entity(Template::empty("dummy"), Pos::new(4, 1)).unwrap();
+		for time in 1..30 {
+			room.update(Timestamp(time));
+		}
+		let healths = room.world.read_component::<Health>();
+		let positions = room.world.read_component::<Position>();
+		let survivors: Vec<Pos> = (&healths, &positions).join().map(|(_, position)| position.pos).collect();
+		assert_eq!(survivors, vec![Pos::new(4, 1)]);
+	}
+	
+	#[test]
+	fn every_ai_acts_each_step() {
+		let mut room = field_room(json!({"assemblages": {
+			"guard": {"sprite": "guard", "height": 1, "components": [
+				["MonsterAI", {"move_chance": 0.0, "view_distance": 1, "homesickness": 0.0, "home": []}],
+				["Fighter", {"damage": 100, "cooldown": 1}],
+				["Faction", {"faction": "evil"}]
+			]},
+			"dummy": {"sprite": "dummy", "height": 1, "components": [
+				["Health", {"health": 1000, "maxhealth": 1000}],
+				["Faction", {"faction": "good"}]
+			]}
+		}}), &[",,,,,,,,,"]);
+		for x in &[0, 3, 6] {
+			room.create_entity(Template::empty("guard"), Pos::new(*x, 0)).unwrap();
+			room.create_entity(Template::empty("dummy"), Pos::new(*x + 1, 0)).unwrap();
+		}
+		for time in 1..4 {
+			room.update(Timestamp(time));
+		}
+		let healths = room.world.read_component::<Health>();
+		assert!((&healths).join().all(|health| health.health < health.maxhealth));
+	}
+	
+	#[test]
+	fn patrol_walks_around_wall() {
+		let mut room = field_room(json!({
+			"assemblages": {
+				"watchman": {"sprite": "watchman", "height": 1, "components": [
+					["MonsterAI", {"move_chance": 0.0, "view_distance": 3, "homesickness": 0.0, "home": [0, 1], "behaviour": "patrol"}],
+					["Movable", {"cooldown": 1}]
+				]}
+			},
+			"behaviours": {
+				"patrol": {"start": "patrol", "states": {"patrol": {"action": {"patrol": [[0, 0], [2, 0]]}}}}
+			}
+		}), &[",,,,", ",#,,", ",,,,"]);
+		room.create_entity(Template::empty("watchman"), Pos::new(0, 1)).unwrap();
+		let mut visited = HashSet::new();
+		for time in 1..40 {
+			room.update(Timestamp(time));
+			let positions = room.world.read_component::<Position>();
+			let ais = room.world.read_component::<MonsterAI>();
+			visited.extend((&positions, &ais).join().map(|(position, _)| position.pos));
+		}
+		assert!(visited.contains(&Pos::new(2, 1)));
+		assert!(visited.contains(&Pos::new(0, 1)));
+		assert!(!visited.contains(&Pos::new(1, 1)));
+	}
+	
+	#[test]
+	fn behaviour_state_is_kept_after_reload() {
+		let extra = json!({
+			"assemblages": {
+				"watchman": {
+					"arguments": {"state": [], "anchor": []},
+					"sprite": "watchman",
+					"height": 1,
+					"components": [
+						["MonsterAI", {"move_chance": 0.0, "view_distance": 3, "homesickness": 0.0, "home": [], "behaviour": "patrol",
+							"state": {"$arg": "state"}, "anchor": {"$arg": "anchor"}}],
+						["Movable", {"cooldown": 1}]
+					],
+					"extract": {"state": ["MonsterAI", "state"], "anchor": ["MonsterAI", "anchor"]}
+				}
+			},
+			"behaviours": {
+				"patrol": {"start": "wait", "states": {
+					"wait": {"action": "idle", "transitions": [{"when": [{"after": 2}], "to": "patrol"}]},
+					"patrol": {"action": {"patrol": [[0, 0], [3, 0]]}}
+				}}
+			}
+		});
+		let mut room = field_room(extra.clone(), &[",,,,"]);
+		room.create_entity(Template::empty("watchman"), Pos::new(0, 0)).unwrap();
+		for time in 1..6 {
+			room.update(Timestamp(time));
+		}
+		let mut reloaded = field_room(extra, &[",,,,"]);
+		reloaded.load_saved(&room.save());
+		reloaded.update(Timestamp(6));
+		let ais = reloaded.world.read_component::<MonsterAI>();
+		let ai = (&ais).join().next().unwrap();
+		assert_eq!(ai.state.as_deref(), Some("patrol"));
+		assert_eq!(ai.anchor, Some(Pos::new(0, 0)));
+	}
+	
+	#[test]
+	fn players_fight_when_both_enable_pvp() {
+		let mut room = field_room(json!({}), &[",,"]);
+		let (alice, bob) = (PlayerId("alice".to_string()), PlayerId("bob".to_string()));
+		add_player_at(&mut room, PlayerState::new(alice.clone()), 0);
+		add_player_at(&mut room, PlayerState::new(bob.clone()), 1);
+		room.update(Timestamp(1));
+		let mut time = 1;
+		let mut step = |room: &mut Room, id: &PlayerId, control: Control| {
+			room.control_player(id.clone(), control);
+			time += 1;
+			room.update(Timestamp(time));
+		};
+		let bob_health = |room: &Room| room.save_players()[&bob].health.unwrap();
+		step(&mut room, &alice, Control::Attack(vec![Direction::East]));
+		assert_eq!(bob_health(&room), 10);
+		step(&mut room, &alice, Control::Pvp(true));
+		step(&mut room, &alice, Control::Attack(vec![Direction::East]));
+		assert_eq!(bob_health(&room), 10);
+		step(&mut room, &bob, Control::Pvp(true));
+		step(&mut room, &alice, Control::Attack(vec![Direction::East]));
+		assert!(bob_health(&room) < 10);
+	}
+	
+	#[test]
+	fn dead_player_leaves_grave() {
+		let mut room = field_room(json!({
+			"assemblages": {
+				"grave": {
+					"arguments": {"owner": null, "items": [], "public_at": 0},
+					"components": [
+						["Grave", {"owner": {"$arg": "owner"}, "items": {"$arg": "items"}, "public_at": {"$arg": "public_at"}}],
+						["Interactable", {"typ": "grave", "arg": []}]
+					],
+					"sprite": "grave",
+					"height": 0.5,
+					"extract": {"items": ["Grave", "items"]}
+				}
+			},
+			"items": {
+				"stone": {}
+			}
+		}), &[",,"]);
+		room.set_death_penalty(DeathPenalty{drop: DeathDrop::All, private_time: 100});
+		let (alice, bob) = (PlayerId("alice".to_string()), PlayerId("bob".to_string()));
+		add_player_at(&mut room, PlayerState::create(alice.clone(), RoomId("test".to_string()), vec![(ItemId("stone".to_string()), false)], 10), 0);
+		add_player_at(&mut room, PlayerState::new(bob.clone()), 1);
+		room.update(Timestamp(1));
+		{
+			let alice_ent = room.world.fetch::<Players>().entities[&alice];
+			AttackInbox::add_attack(&mut room.world.write_component::<AttackInbox>(), alice_ent, AttackType::Poison(100), &[], None);
+		}
+		room.update(Timestamp(2));
+		room.update(Timestamp(3));
+		assert!(room.save_players()[&alice].inventory.is_empty());
+		assert_eq!(room.world.read_component::<Grave>().join().next().unwrap().items, vec![ItemId("stone".to_string())]);
+		let saved_grave = room.save().changes[&Pos::new(0, 0)].iter().find(|template| template.name.0 == "grave").unwrap().clone();
+		assert_eq!(saved_grave.kwargs["owner"], Parameter::String("alice".to_string()));
+		assert_eq!(saved_grave.kwargs["public_at"], Parameter::Int(102));
+		room.control_player(bob.clone(), Control::Interact(vec![Direction::West], None));
+		room.update(Timestamp(4));
+		assert!(room.save_players()[&bob].inventory.is_empty());
+		room.control_player(bob.clone(), Control::Interact(vec![Direction::West], None));
+		room.update(Timestamp(103));
+		room.update(Timestamp(104));
+		assert_eq!(room.save_players()[&bob].inventory, vec![(ItemId("stone".to_string()), false)]);
+		assert!(room.world.read_component::<Grave>().is_empty());
+	}
+	
+	#[test]
+	fn poison_wears_off() {
+		let encyclopedia = Encyclopedia::deserialize(&json!({
+			"assemblages": {
+				"dummy": {"sprite": "dummy", "height": 1, "components": [
+					["Health", {"health": 20, "maxhealth": 20}]
+				]}
+			}
+		})).unwrap();
+		let mut room = Room::new(RoomId("test".to_string()), encyclopedia, RoomType::Normal);
+		room.create_entity(Template::empty("dummy"), Pos::new(0, 0)).unwrap();
+		room.update(Timestamp(4));
+		{
+			let entities = room.world.entities();
+			let healths = room.world.read_component::<Health>();
+			let (dummy, _) = (&entities, &healths).join().next().unwrap();
+			let poison = Effect{kind: EffectKind::Poison, amount: 2, duration: 30};
+			AttackInbox::add_attack(&mut room.world.write_component::<AttackInbox>(), dummy, AttackType::Attack(0), &[poison], None);
+		}
+		for time in 5..100 {
+			room.update(Timestamp(time));
+		}
+		let healths = room.world.read_component::<Health>();
+		assert_eq!((&healths).join().next().unwrap().health, 14);
+		assert!(room.world.read_component::<StatusEffects>().is_empty());
+	}
 }
-
-
@@ -0,0 +1,366 @@
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use serde::{Deserialize};
+use serde_json::Value;
+use rand::{Rng, SeedableRng, seq::SliceRandom};
+use rand_chacha::ChaCha8Rng;
+use ring::digest;
+
+use crate::{
+	Pos,
+	RoomId,
+	Result,
+	aerr,
+	roomtemplate::{RoomTemplate, TemplateList},
+	resources::{RoomFlags, Neighbours}
+};
+
+/// How the walls of a generated room are laid out
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum Generator {
+	/// Random noise smoothed into caves
+	Cave {
+		#[serde(default = "default_fill")]
+		fill: f64,
+		#[serde(default = "default_smoothing")]
+		smoothing: usize
+	},
+	/// Corridors of width 1 between walls
+	Maze,
+	/// No walls, only the scattered objects
+	Scatter
+}
+
+fn default_fill() -> f64 {0.45}
+fn default_smoothing() -> usize {4}
+
+/// A map file that is generated instead of drawn
+#[derive(Debug, Clone, Deserialize)]
+pub struct GeneratedRoom {
+	pub width: i64,
+	pub height: i64,
+	pub spawn: Pos,
+	#[serde(default)]
+	pub places: HashMap<String, Pos>,
+	pub generator: Generator,
+	pub floor: TemplateList,
+	pub wall: TemplateList,
+	/// Objects put on random floor tiles, with the chance per tile
+	#[serde(default)]
+	pub scatter: Vec<(TemplateList, f64)>,
+	/// Objects put on named places
+	#[serde(default)]
+	pub objects: HashMap<String, TemplateList>,
+	#[serde(default)]
+	pub flags: RoomFlags,
+	#[serde(default)]
+	pub neighbours: Neighbours
+}
+
+impl GeneratedRoom {
+
+	/// Read a generated map file for the given room.
+	/// In all strings "{level}" is replaced by the number after the '+' in the room id, and "{next}" by the number after that
+	pub fn from_value(mut value: Value, id: &RoomId) -> Result<Self> {
+		let level: i64 = id.0.split_once('+').and_then(|(_name, suffix)| suffix.parse().ok()).unwrap_or(0);
+		substitute(&mut value, &[("{level}", level.to_string()), ("{next}", (level + 1).to_string())]);
+		let room = Self::deserialize(value)?;
+		let walls = Walls::new(room.width, room.height);
+		if !walls.contains(room.spawn) {
+			return Err(aerr!("spawn {:?} is outside the room", room.spawn));
+		}
+		for (name, place) in room.places.iter() {
+			if !walls.contains(*place) {
+				return Err(aerr!("place {} at {:?} is outside the room", name, place));
+			}
+		}
+		Ok(room)
+	}
+
+	pub fn generate(&self, id: &RoomId) -> RoomTemplate {
+		// a named algorithm, because StdRng may give other numbers in another version of rand
+		let mut rng = ChaCha8Rng::seed_from_u64(seed(id));
+		let mut walls = Walls::new(self.width, self.height);
+		let mut fixed: Vec<Pos> = self.places.values().cloned().collect();
+		fixed.push(self.spawn);
+		match self.generator {
+			Generator::Cave{fill, smoothing} => {
+				walls.noise(&mut rng, fill);
+				for _ in 0..smoothing {
+					walls.smooth();
+				}
+				for place in fixed.iter() {
+					walls.carve_path(self.spawn, *place);
+				}
+				walls.fill_unreachable(self.spawn);
+			}
+			Generator::Maze => {
+				walls.maze(&mut rng, self.spawn);
+				for place in fixed.iter() {
+					walls.carve_path(*place, walls.nearest_maze_cell(*place));
+				}
+			}
+			Generator::Scatter => {
+				walls.cells.iter_mut().for_each(|cell| *cell = false);
+			}
+		}
+		let mut field = Vec::with_capacity((self.width * self.height) as usize);
+		for y in 0..self.height {
+			for x in 0..self.width {
+				let pos = Pos::new(x, y);
+				if walls.is_wall(pos) {
+					field.push(self.wall.clone().into_vec());
+					continue;
+				}
+				let mut templates = self.floor.clone().into_vec();
+				if !fixed.contains(&pos) {
+					for (objects, chance) in self.scatter.iter() {
+						if rng.gen::<f64>() < *chance {
+							templates.append(&mut objects.clone().into_vec());
+							break;
+						}
+					}
+				}
+				field.push(templates);
+			}
+		}
+		for (name, objects) in self.objects.iter() {
+			if let Some(place) = self.places.get(name) {
+				if let Some(templates) = field.get_mut((place.x + place.y * self.width) as usize) {
+					templates.append(&mut objects.clone().into_vec());
+				}
+			}
+		}
+		RoomTemplate {
+			size: (self.width, self.height),
+			spawn: self.spawn,
+			field,
+			places: self.places.clone(),
+			flags: self.flags.clone(),
+			neighbours: self.neighbours.clone()
+		}
+	}
+}
+
+/// A seed that is the same for the same room id on every machine and every run
+fn seed(id: &RoomId) -> u64 {
+	let hash = digest::digest(&digest::SHA256, id.0.as_bytes());
+	let mut bytes = [0; 8];
+	bytes.copy_from_slice(&hash.as_ref()[..8]);
+	u64::from_le_bytes(bytes)
+}
+
+fn substitute(value: &mut Value, replacements: &[(&str, String)]) {
+	match value {
+		Value::String(text) => {
+			for (from, to) in replacements {
+				*text = text.replace(from, to);
+			}
+		}
+		Value::Array(values) => values.iter_mut().for_each(|v| substitute(v, replacements)),
+		Value::Object(values) => values.values_mut().for_each(|v| substitute(v, replacements)),
+		_ => {}
+	}
+}
+
+struct Walls {
+	width: i64,
+	height: i64,
+	cells: Vec<bool>
+}
+
+impl Walls {
+
+	fn new(width: i64, height: i64) -> Self {
+		Self {
+			width,
+			height,
+			cells: vec![true; (width * height).max(0) as usize]
+		}
+	}
+
+	fn contains(&self, pos: Pos) -> bool {
+		pos.x >= 0 && pos.y >= 0 && pos.x < self.width && pos.y < self.height
+	}
+
+	fn is_wall(&self, pos: Pos) -> bool {
+		!self.contains(pos) || self.cells[(pos.x + pos.y * self.width) as usize]
+	}
+
+	fn set(&mut self, pos: Pos, wall: bool) {
+		if self.contains(pos) {
+			self.cells[(pos.x + pos.y * self.width) as usize] = wall;
+		}
+	}
+
+	fn is_border(&self, pos: Pos) -> bool {
+		pos.x == 0 || pos.y == 0 || pos.x == self.width - 1 || pos.y == self.height - 1
+	}
+
+	fn positions(&self) -> Vec<Pos> {
+		(0..self.height).flat_map(|y| (0..self.width).map(move |x| Pos::new(x, y))).collect()
+	}
+
+	fn noise(&mut self, rng: &mut ChaCha8Rng, fill: f64) {
+		for pos in self.positions() {
+			let wall = self.is_border(pos) || rng.gen::<f64>() < fill;
+			self.set(pos, wall);
+		}
+	}
+
+	/// A tile becomes a wall when most of its neighbours are walls, and floor when most are floor
+	fn smooth(&mut self) {
+		let mut cells = self.cells.clone();
+		for pos in self.positions() {
+			let neighbour_walls = (-1..=1)
+				.flat_map(|dy| (-1..=1).map(move |dx| Pos::new(dx, dy)))
+				.filter(|d| *d != Pos::new(0, 0) && self.is_wall(pos + *d))
+				.count();
+			if self.is_border(pos) || neighbour_walls > 4 {
+				cells[(pos.x + pos.y * self.width) as usize] = true;
+			} else if neighbour_walls < 4 {
+				cells[(pos.x + pos.y * self.width) as usize] = false;
+			}
+		}
+		self.cells = cells;
+	}
+
+	/// Make the tiles on a path (first horizontal, then vertical) between two positions floor
+	fn carve_path(&mut self, from: Pos, to: Pos) {
+		let mut pos = from;
+		self.set(pos, false);
+		while pos.x != to.x {
+			pos.x += (to.x - pos.x).signum();
+			self.set(pos, false);
+		}
+		while pos.y != to.y {
+			pos.y += (to.y - pos.y).signum();
+			self.set(pos, false);
+		}
+	}
+
+	fn fill_unreachable(&mut self, start: Pos) {
+		let mut reachable = HashSet::new();
+		let mut queue = VecDeque::new();
+		if !self.is_wall(start) {
+			reachable.insert(start);
+			queue.push_back(start);
+		}
+		while let Some(pos) = queue.pop_front() {
+			for d in &[Pos::new(1, 0), Pos::new(-1, 0), Pos::new(0, 1), Pos::new(0, -1)] {
+				let next = pos + *d;
+				if !self.is_wall(next) && reachable.insert(next) {
+					queue.push_back(next);
+				}
+			}
+		}
+		for pos in self.positions() {
+			if !reachable.contains(&pos) {
+				self.set(pos, true);
+			}
+		}
+	}
+
+	/// The maze corridors run over the odd coordinates
+	fn nearest_maze_cell(&self, pos: Pos) -> Pos {
+		let odd = |coord: i64, size: i64| {
+			let max = if size % 2 == 0 {size - 3} else {size - 2};
+			(coord - (1 - coord % 2)).max(1).min(max.max(1))
+		};
+		Pos::new(odd(pos.x, self.width), odd(pos.y, self.height))
+	}
+
+	/// Randomized depth-first search over the odd coordinates
+	fn maze(&mut self, rng: &mut ChaCha8Rng, start: Pos) {
+		let start = self.nearest_maze_cell(start);
+		self.set(start, false);
+		let mut stack = vec![start];
+		while let Some(&pos) = stack.last() {
+			let mut options: Vec<Pos> = [Pos::new(2, 0), Pos::new(-2, 0), Pos::new(0, 2), Pos::new(0, -2)]
+				.iter()
+				.map(|d| pos + *d)
+				.filter(|next| next.x > 0 && next.y > 0 && next.x < self.width - 1 && next.y < self.height - 1 && self.is_wall(*next))
+				.collect();
+			options.shuffle(rng);
+			if let Some(next) = options.pop() {
+				self.set(Pos::new((pos.x + next.x) / 2, (pos.y + next.y) / 2), false);
+				self.set(next, false);
+				stack.push(next);
+			} else {
+				stack.pop();
+			}
+		}
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use serde_json::json;
+	use crate::Template;
+
+	fn generated(generator: Value) -> GeneratedRoom {
+		GeneratedRoom::from_value(json!({
+			"width": 30,
+			"height": 20,
+			"spawn": [3, 3],
+			"places": {"stairdown": [26, 16]},
+			"generator": generator,
+			"floor": "ground",
+			"wall": ["ground", "rock"],
+			"scatter": [["stone", 0.1]],
+			"objects": {"stairdown": {":template": "portal", "destination": "mine+{next}"}}
+		}), &RoomId("mine+3".to_string())).unwrap()
+	}
+
+	fn is_wall(template: &RoomTemplate, pos: Pos) -> bool {
+		template.field[(pos.x + pos.y * template.size.0) as usize].contains(&Template::empty("rock"))
+	}
+
+	#[test]
+	fn same_room_id_gives_same_room() {
+		let room = generated(json!({"type": "cave"}));
+		let field = room.generate(&RoomId("mine+3".to_string())).field;
+		assert_eq!(field, room.generate(&RoomId("mine+3".to_string())).field);
+		assert_ne!(field, room.generate(&RoomId("mine+4".to_string())).field);
+	}
+
+	#[test]
+	fn places_are_reachable() {
+		for generator in [json!({"type": "cave"}), json!({"type": "maze"}), json!({"type": "scatter"})] {
+			let template = generated(generator).generate(&RoomId("mine+3".to_string()));
+			let mut walls = Walls::new(30, 20);
+			for pos in walls.positions() {
+				walls.set(pos, is_wall(&template, pos));
+			}
+			walls.fill_unreachable(Pos::new(3, 3));
+			assert!(!walls.is_wall(Pos::new(3, 3)));
+			assert!(!walls.is_wall(Pos::new(26, 16)));
+		}
+	}
+
+	#[test]
+	fn places_outside_the_room_are_rejected() {
+		let room = |place: [i64; 2]| GeneratedRoom::from_value(json!({
+			"width": 30,
+			"height": 20,
+			"spawn": [3, 3],
+			"places": {"stairdown": place},
+			"generator": {"type": "scatter"},
+			"floor": "ground",
+			"wall": "rock"
+		}), &RoomId("mine+3".to_string()));
+		assert!(room([29, 19]).is_ok());
+		assert!(room([30, 0]).is_err());
+		assert!(room([0, -1]).is_err());
+	}
+
+	#[test]
+	fn level_is_substituted() {
+		let template = generated(json!({"type": "maze"})).generate(&RoomId("mine+3".to_string()));
+		let stairs = &template.field[26 + 16 * 30];
+		assert_eq!(stairs.last().unwrap().kwargs.get("destination"), Some(&crate::parameter::Parameter::String("mine+4".to_string())));
+	}
+}
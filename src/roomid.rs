@@ -3,7 +3,7 @@ use std::fmt;
 use std::collections::HashMap;
 use serde::{Serialize, Deserialize};
 
-#[derive(Debug, PartialEq, Eq, Clone, Hash, Serialize, Deserialize)]
+#[derive(Debug, Default, PartialEq, Eq, Clone, Hash, Serialize, Deserialize)]
 pub struct RoomId(pub String);
 
 impl RoomId {
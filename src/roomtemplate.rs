@@ -1,10 +1,9 @@
-
 use std::collections::HashMap;
 use serde::{Deserialize, Deserializer, de, Serialize};
 use crate::{
 	Pos,
 	Template,
-	resources::RoomFlags
+	resources::{RoomFlags, Neighbours}
 };
 
 #[derive(Debug, Clone)]
@@ -13,59 +12,161 @@ pub struct RoomTemplate {
 	pub spawn: Pos,
 	pub field: Vec<Vec<Template>>,
 	pub places: HashMap<String, Pos>,
-	pub flags: RoomFlags
+	pub flags: RoomFlags,
+	pub neighbours: Neighbours
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
-struct RoomTemplateSave {
+pub struct RoomTemplateSave {
 	pub width: i64,
 	pub height: i64,
 	pub spawn: Pos,
-	pub field: Vec<String>,
-	pub mapping: HashMap<char, TemplateList>,
+	#[serde(flatten)]
+	pub base: Layer,
+	#[serde(default)]
+	pub layers: Vec<NamedLayer>,
+	#[serde(default)]
+	pub stamps: Vec<StampPlacement>,
 	#[serde(default)]
 	pub places: HashMap<String, Pos>,
 	#[serde(default)]
-	pub flags: RoomFlags
+	pub flags: RoomFlags,
+	#[serde(default)]
+	pub neighbours: Neighbours
+}
+
+/// A grid of characters with the templates that each character stands for.
+/// A space that is not in the mapping is empty
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Layer {
+	#[serde(default)]
+	pub field: Vec<String>,
+	#[serde(default)]
+	pub mapping: HashMap<char, TemplateList>
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NamedLayer {
+	pub name: String,
+	#[serde(flatten)]
+	pub layer: Layer
+}
+
+/// A map fragment that can be placed in several maps
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Stamp {
+	#[serde(flatten)]
+	pub base: Layer,
+	#[serde(default)]
+	pub layers: Vec<NamedLayer>,
+	/// places in the stamp, relative to the stamp offset
+	#[serde(default)]
+	pub places: HashMap<String, Pos>
 }
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StampPlacement {
+	pub stamp: String,
+	pub offset: Pos,
+	/// Whether the stamp replaces everything on the tiles it covers, instead of being put on top
+	#[serde(default)]
+	pub replace: bool
+}
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(untagged)]
-enum TemplateList {
+pub enum TemplateList {
 	Single(Template),
 	List(Vec<Template>)
 }
-impl<'de> Deserialize<'de> for RoomTemplate {
-	fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
-	where D: Deserializer<'de>,
-	{
-		let rts = RoomTemplateSave::deserialize(deserializer)?;
-		let mut field = Vec::new();
-		let width = rts.width as usize;
-		let height = rts.height as usize;
-		field.resize_with(width * height, Vec::new);
-		for (y, row) in rts.field.iter().take(height).enumerate() {
-			for (x, ch) in row.chars().take(width).enumerate() {
-				let templates = rts.mapping.get(&ch).ok_or(de::Error::custom(format!("char {}not found in mapping", ch)))?.clone();
-				field[x + y * width] = match templates {
-					TemplateList::Single(temp) => vec![temp],
-					TemplateList::List(temps) => temps
+
+impl TemplateList {
+	pub fn into_vec(self) -> Vec<Template> {
+		match self {
+			TemplateList::Single(temp) => vec![temp],
+			TemplateList::List(temps) => temps
+		}
+	}
+}
+
+impl Layer {
+	fn draw(&self, field: &mut [Vec<Template>], (width, height): (i64, i64), offset: Pos, replace: bool) -> Result<(), String> {
+		for (y, row) in self.field.iter().enumerate() {
+			for (x, ch) in row.chars().enumerate() {
+				let pos = offset + Pos::new(x as i64, y as i64);
+				if pos.x < 0 || pos.y < 0 || pos.x >= width || pos.y >= height {
+					continue;
+				}
+				let templates = match self.mapping.get(&ch) {
+					Some(templates) => templates.clone().into_vec(),
+					None if ch == ' ' => continue,
+					None => return Err(format!("char '{}' at ({}, {}) not found in mapping", ch, x, y))
+				};
+				let cell = &mut field[(pos.x + pos.y * width) as usize];
+				if replace {
+					cell.clear();
 				}
+				cell.extend(templates);
 			}
 		}
-		Ok(Self {
-			size: (rts.width, rts.height),
-			spawn: rts.spawn,
+		Ok(())
+	}
+}
+
+impl Stamp {
+	fn draw(&self, field: &mut [Vec<Template>], size: (i64, i64), offset: Pos, replace: bool) -> Result<(), String> {
+		self.base.draw(field, size, offset, replace)?;
+		for named in self.layers.iter() {
+			named.layer.draw(field, size, offset, false).map_err(|e| format!("{} in layer {}", e, named.name))?;
+		}
+		Ok(())
+	}
+}
+
+impl RoomTemplateSave {
+	
+	/// Draw all layers and stamps. The stamps are looked up by name
+	pub fn build(self, stamps: &HashMap<String, Stamp>) -> Result<RoomTemplate, String> {
+		let size = (self.width, self.height);
+		let mut field = Vec::new();
+		field.resize_with((self.width * self.height).max(0) as usize, Vec::new);
+		self.base.draw(&mut field, size, Pos::new(0, 0), false)?;
+		for named in self.layers.iter() {
+			named.layer.draw(&mut field, size, Pos::new(0, 0), false).map_err(|e| format!("{} in layer {}", e, named.name))?;
+		}
+		let mut places = HashMap::new();
+		for placement in self.stamps.iter() {
+			let stamp = stamps.get(&placement.stamp).ok_or(format!("stamp {} not found", placement.stamp))?;
+			stamp.draw(&mut field, size, placement.offset, placement.replace).map_err(|e| format!("{} in stamp {}", e, placement.stamp))?;
+			for (name, pos) in stamp.places.iter() {
+				places.insert(name.clone(), placement.offset + *pos);
+			}
+		}
+		places.extend(self.places);
+		Ok(RoomTemplate {
+			size,
+			spawn: self.spawn,
 			field,
-			places: rts.places,
-			flags: rts.flags
+			places,
+			flags: self.flags,
+			neighbours: self.neighbours
 		})
 	}
 }
 
+impl<'de> Deserialize<'de> for RoomTemplate {
+	fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+	where D: Deserializer<'de>,
+	{
+		RoomTemplateSave::deserialize(deserializer)?.build(&HashMap::new()).map_err(de::Error::custom)
+	}
+}
+
 #[cfg(test)]
 mod tests {
 	use super::*;
 	use serde_json::json;
+	use crate::{RoomId, controls::Direction, hashmap};
 	
 	#[test]
 	fn simple_deserialize() {
@@ -87,4 +188,60 @@ mod tests {
 			}
 		})).unwrap();
 	}
+	
+	#[test]
+	fn neighbours_deserialize() {
+		let template = RoomTemplate::deserialize(&json!({
+			"width": 1,
+			"height": 1,
+			"spawn": [0, 0],
+			"field": [","],
+			"mapping": {",": "grass"},
+			"neighbours": {"east": "town", "n": "forest"}
+		})).unwrap();
+		assert_eq!(template.neighbours.0.get(&Direction::East), Some(&RoomId("town".to_string())));
+		assert_eq!(template.neighbours.0.get(&Direction::North), Some(&RoomId("forest".to_string())));
+	}
+	
+	#[test]
+	fn layers_and_stamps() {
+		let save = RoomTemplateSave::deserialize(&json!({
+			"width": 4,
+			"height": 2,
+			"spawn": [0, 0],
+			"field": [",,,,", ",,,,"],
+			"mapping": {",": "grass"},
+			"layers": [
+				{"name": "objects", "field": ["  * ", "*"], "mapping": {"*": "stone"}}
+			],
+			"stamps": [
+				{"stamp": "hut", "offset": [2, 0], "replace": true}
+			]
+		})).unwrap();
+		let stamp = Stamp::deserialize(&json!({
+			"field": [" #", "##"],
+			"mapping": {"#": ["floor", "wall"]},
+			"places": {"hutcorner": [1, 1]}
+		})).unwrap();
+		let template = save.build(&hashmap!{"hut".to_string() => stamp}).unwrap();
+		let names = |x: i64, y: i64| template.field[(x + y * 4) as usize].iter().map(|t| t.name.0.as_str()).collect::<Vec<&str>>();
+		assert_eq!(names(0, 0), vec!["grass"]);
+		assert_eq!(names(2, 0), vec!["grass", "stone"]);
+		assert_eq!(names(0, 1), vec!["grass", "stone"]);
+		assert_eq!(names(3, 0), vec!["floor", "wall"]);
+		assert_eq!(names(2, 1), vec!["floor", "wall"]);
+		assert_eq!(template.places.get("hutcorner"), Some(&Pos::new(3, 1)));
+	}
+	
+	#[test]
+	fn unmapped_char_is_error() {
+		let save = RoomTemplateSave::deserialize(&json!({
+			"width": 3,
+			"height": 2,
+			"spawn": [0, 0],
+			"field": [",,,", ",x,"],
+			"mapping": {",": "grass"}
+		})).unwrap();
+		assert_eq!(save.build(&HashMap::new()).unwrap_err(), "char 'x' at (1, 1) not found in mapping");
+	}
 }
@@ -5,36 +5,45 @@ use serde::{Serialize, Deserialize, Serializer, Deserializer};
 use crate::{
 	Pos,
 	Template,
+	Timestamp
 };
 
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct SaveState {
-	pub changes: HashMap<Pos, Vec<Template>>
+	pub changes: HashMap<Pos, Vec<Template>>,
+	/// The room time when it was saved
+	pub time: Option<Timestamp>
 }
 
 impl SaveState {
 	
 	pub fn new() -> Self {
 		Self {
-			changes: HashMap::new()
+			changes: HashMap::new(),
+			time: None
 		}
 	}
 }
 
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
-struct SaveStateVec {pub changes: Vec<(Pos, Vec<Template>)>}
+struct SaveStateVec {
+	pub changes: Vec<(Pos, Vec<Template>)>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub time: Option<Timestamp>
+}
 
 impl Serialize for SaveState {
 	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
 	where S: Serializer {
-		SaveStateVec{changes: self.changes.clone().into_iter().collect()}.serialize(serializer)
+		SaveStateVec{changes: self.changes.clone().into_iter().collect(), time: self.time}.serialize(serializer)
 	}
 }
 impl<'de> Deserialize<'de> for SaveState {
 	fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
 	where D: Deserializer<'de> {
-		Ok(Self{changes: SaveStateVec::deserialize(deserializer)?.changes.into_iter().collect()})
+		let state = SaveStateVec::deserialize(deserializer)?;
+		Ok(Self{changes: state.changes.into_iter().collect(), time: state.time})
 	}
 }
 
@@ -59,7 +68,7 @@ mod tests{
 	fn test_change_serialize(){
 		assert_eq!(
 			serde_json::to_value(
-				SaveState{changes: hashmap!{Pos::new(5,2) => vec![Template::empty("grass"), Template::empty("tree")]}}
+				SaveState{changes: hashmap!{Pos::new(5,2) => vec![Template::empty("grass"), Template::empty("tree")]}, time: None}
 			).unwrap(),
 			json!({"changes":[[[5,2],["grass", "tree"]]]})
 		);
@@ -72,7 +81,14 @@ mod tests{
 			SaveState{changes: hashmap!{
 				Pos::new(1,1) => vec![Template::empty("grass")],
 				Pos::new(5,2) => vec![Template::empty("grass"), Template::empty("tree")]
-			}}
+			}, time: None}
 		);
 	}
+	
+	#[test]
+	fn test_time_roundtrip(){
+		let state = SaveState{changes: HashMap::new(), time: Some(Timestamp(1234))};
+		assert_eq!(serde_json::to_value(&state).unwrap(), json!({"changes":[], "time": 1234}));
+		assert_eq!(SaveState::deserialize(json!({"changes":[], "time": 1234})).unwrap(), state);
+	}
 }
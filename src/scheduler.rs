@@ -0,0 +1,153 @@
+
+use std::time::{Duration, Instant};
+use std::sync::mpsc::{Receiver, RecvTimeoutError};
+
+/// Keeps the steps on fixed boundaries, so game time does not drift when steps take long
+pub struct Scheduler {
+	step_duration: Duration,
+	max_catchup: u32,
+	next_step: Instant,
+	pub metrics: TickMetrics
+}
+
+impl Scheduler {
+
+	pub fn new(step_duration: Duration, max_catchup: u32, now: Instant) -> Self {
+		Self {
+			step_duration,
+			max_catchup,
+			next_step: now,
+			metrics: TickMetrics::default()
+		}
+	}
+
+	/// Register that a step that started at `started` is finished at `now`, and return how long to wait before the next step.
+	/// When the server is behind it runs the next steps immediately to catch up, but when it is more than max_catchup steps behind those steps are skipped.
+	pub fn finish_step(&mut self, started: Instant, now: Instant) -> Duration {
+		self.metrics.record(now.saturating_duration_since(started));
+		self.next_step += self.step_duration;
+		if now <= self.next_step {
+			return self.next_step - now;
+		}
+		let behind = ((now - self.next_step).as_nanos() / self.step_duration.as_nanos().max(1)) as u32;
+		if behind > self.max_catchup {
+			println!("Warning: server is {} steps behind; skipping them", behind);
+			self.metrics.skipped += behind as usize;
+			self.next_step = now;
+		} else {
+			self.metrics.overruns += 1;
+		}
+		Duration::from_secs(0)
+	}
+
+	/// Wait for the given duration, or until a stop signal comes.
+	/// Returns whether the server should keep running.
+	pub fn wait(&self, duration: Duration, stop: &Receiver<()>) -> bool {
+		match stop.recv_timeout(duration) {
+			Err(RecvTimeoutError::Timeout) => true,
+			Ok(()) | Err(RecvTimeoutError::Disconnected) => false
+		}
+	}
+}
+
+/// Durations of the steps since the last report
+#[derive(Debug, Default)]
+pub struct TickMetrics {
+	/// Durations are only kept when they are reported, otherwise they would pile up
+	pub enabled: bool,
+	durations: Vec<Duration>,
+	overruns: usize,
+	skipped: usize
+}
+
+impl TickMetrics {
+
+	fn record(&mut self, duration: Duration) {
+		if self.enabled {
+			self.durations.push(duration);
+		}
+	}
+
+	/// Summarise the recorded steps and start over
+	pub fn report(&mut self) -> Option<String> {
+		if self.durations.is_empty() {
+			return None;
+		}
+		let mut durations = std::mem::take(&mut self.durations);
+		durations.sort();
+		let total: Duration = durations.iter().sum();
+		let percentile = |p: usize| durations[(durations.len() - 1) * p / 100];
+		let report = format!(
+			"{} steps: mean {:?}, p50 {:?}, p90 {:?}, p99 {:?}, max {:?}, {} overruns, {} skipped",
+			durations.len(),
+			total / durations.len() as u32,
+			percentile(50),
+			percentile(90),
+			percentile(99),
+			durations[durations.len() - 1],
+			self.overruns,
+			self.skipped
+		);
+		self.overruns = 0;
+		self.skipped = 0;
+		Some(report)
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn ms(n: u64) -> Duration {
+		Duration::from_millis(n)
+	}
+
+	#[test]
+	fn waits_until_step_boundary() {
+		let start = Instant::now();
+		let mut scheduler = Scheduler::new(ms(100), 5, start);
+		assert_eq!(scheduler.finish_step(start, start + ms(30)), ms(70));
+		assert_eq!(scheduler.finish_step(start + ms(100), start + ms(160)), ms(40));
+	}
+
+	#[test]
+	fn catches_up_after_overrun() {
+		let start = Instant::now();
+		let mut scheduler = Scheduler::new(ms(100), 5, start);
+		assert_eq!(scheduler.finish_step(start, start + ms(250)), ms(0));
+		assert_eq!(scheduler.finish_step(start + ms(250), start + ms(260)), ms(0));
+		assert_eq!(scheduler.finish_step(start + ms(260), start + ms(270)), ms(30));
+		assert_eq!(scheduler.metrics.overruns, 2);
+	}
+
+	#[test]
+	fn skips_when_far_behind() {
+		let start = Instant::now();
+		let mut scheduler = Scheduler::new(ms(100), 5, start);
+		assert_eq!(scheduler.finish_step(start, start + ms(1000)), ms(0));
+		assert_eq!(scheduler.metrics.skipped, 9);
+		assert_eq!(scheduler.finish_step(start + ms(1000), start + ms(1010)), ms(90));
+	}
+
+	#[test]
+	fn report_resets_metrics() {
+		let mut metrics = TickMetrics{enabled: true, ..TickMetrics::default()};
+		assert!(metrics.report().is_none());
+		metrics.record(ms(10));
+		metrics.record(ms(30));
+		assert!(metrics.report().unwrap().starts_with("2 steps: mean 20ms"));
+		assert!(metrics.report().is_none());
+	}
+
+	#[test]
+	fn disabled_metrics_keep_no_durations() {
+		let start = Instant::now();
+		let mut scheduler = Scheduler::new(ms(100), 5, start);
+		for step in 0..10 {
+			scheduler.finish_step(start + ms(step * 100), start + ms(step * 100 + 10));
+		}
+		assert!(scheduler.metrics.durations.is_empty());
+		assert!(scheduler.metrics.report().is_none());
+	}
+}
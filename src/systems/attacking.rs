@@ -5,6 +5,7 @@ use specs::{
 	ReadStorage,
 	WriteStorage,
 	Write,
+	Read,
 	System,
 	Entities,
 	Join
@@ -15,15 +16,22 @@ use crate::{
 		Health,
 		AttackInbox,
 		AttackType,
+		Inventory,
+		Stats,
+		Stat,
+		StatusEffects,
+		equipment::mitigate,
 		Trigger,
 		TriggerBox,
 		Position,
 		Autofight,
 		Ear,
 		ear::{Notification, HealthNotification::{Attack, Damage, Heal}, say},
-		Visible
+		Visible,
+		Player,
+		Serialise
 	},
-	resources::NewEntities,
+	resources::{NewEntities, Time, Unsaved},
 	Template,
 	util
 };
@@ -40,9 +48,16 @@ impl <'a> System<'a> for Attacking {
 		Write<'a, NewEntities>,
 		WriteStorage<'a, Autofight>,
 		WriteStorage<'a, Ear>,
-		ReadStorage<'a, Visible>
+		ReadStorage<'a, Visible>,
+		ReadStorage<'a, Inventory>,
+		WriteStorage<'a, Stats>,
+		WriteStorage<'a, StatusEffects>,
+		Read<'a, Time>,
+		ReadStorage<'a, Player>,
+		ReadStorage<'a, Serialise>,
+		Write<'a, Unsaved>
 	);
-	fn run(&mut self, (entities, mut attackeds, mut healths, mut triggerboxes, positions, mut new, mut autofighters, mut ears, visibles): Self::SystemData) {
+	fn run(&mut self, (entities, mut attackeds, mut healths, mut triggerboxes, positions, mut new, mut autofighters, mut ears, visibles, inventories, mut stats, mut statuses, time, players, serialisers, mut unsaved): Self::SystemData) {
 		
 		for (entity, attacked, autofighter) in (&entities, &attackeds, &mut autofighters).join() {
 			for attack in &attacked.messages {
@@ -57,6 +72,8 @@ impl <'a> System<'a> for Attacking {
 		}
 		for (target, health, attacked) in (&entities, &mut healths, &mut attackeds).join() {
 			let target_name = visibles.get(target).map(|v| v.name.as_str()).unwrap_or("?").to_string();
+			let defence = *Stats::total(stats.get(target), inventories.get(target), statuses.get(target)).get(&Stat::Defence).unwrap_or(&0);
+			let health_before = health.health;
 			let mut wounded = false;
 			let mut attackers = Vec::new();
 			let mut attacker_names = Vec::new();
@@ -66,31 +83,62 @@ impl <'a> System<'a> for Attacking {
 				).to_string();
 				match attack.typ {
 					AttackType::Attack(strength) => {
-						let damage = rand::thread_rng().gen_range(0, strength+1);
+						let roll = rand::thread_rng().gen_range(0, strength+1);
+						let damage = mitigate(roll, defence);
+						let mitigated = roll - damage;
 						health.health -= damage;
 						if damage > 0 {
 							wounded = true;
 							if let Some(actor) = attack.attacker {
-								say(&mut ears, actor, Notification::Health{actor: actor_name.clone(), target: target_name.clone(), amount: damage, typ: Attack});
 								attackers.push(actor);
 								attacker_names.push(actor_name.clone());
+								Stats::train(&mut stats, &mut ears, actor, Stat::Strength, damage);
 							}
-							say(&mut ears, target, Notification::Health{actor: actor_name.clone(), target: target_name.clone(), amount: damage, typ: Damage});
+						}
+						if roll > 0 {
+							if let Some(actor) = attack.attacker {
+								say(&mut ears, actor, Notification::Health{actor: actor_name.clone(), target: target_name.clone(), amount: damage, mitigated, typ: Attack});
+							}
+							say(&mut ears, target, Notification::Health{actor: actor_name.clone(), target: target_name.clone(), amount: damage, mitigated, typ: Damage});
 						}
 					}
 					AttackType::Heal(healthdiff) => {
-						say(&mut ears, target, Notification::Health{actor: actor_name.clone(), target: target_name.clone(), amount: healthdiff, typ: Heal});
+						say(&mut ears, target, Notification::Health{actor: actor_name.clone(), target: target_name.clone(), amount: healthdiff, mitigated: 0, typ: Heal});
 						health.health += healthdiff;
 					}
+					AttackType::Poison(damage) => {
+						let actor_name = if attack.attacker.is_some() {actor_name} else {"poison".to_string()};
+						health.health -= damage;
+						if damage > 0 {
+							attackers.extend(attack.attacker);
+							attacker_names.push(actor_name.clone());
+							say(&mut ears, target, Notification::Health{actor: actor_name, target: target_name.clone(), amount: damage, mitigated: 0, typ: Damage});
+						}
+					}
+					AttackType::Effect(effect) => {
+						statuses.entry(target).unwrap().or_insert_with(StatusEffects::default).add(effect, time.time);
+						say(&mut ears, target, Notification::Effect{actor: actor_name.clone(), target: target_name.clone(), effect: effect.kind.to_string(), amount: effect.amount, duration: effect.duration});
+					}
 				}
 			}
 			health.health = util::clamp(health.health, 0, health.maxhealth);
+			if health.health != health_before && serialisers.contains(target) {
+				unsaved.0 = true;
+			}
 			if health.health == 0 {
 				TriggerBox::add_message(&mut triggerboxes, target, Trigger::Die);
 				let killers = attacker_names.join(" and ");
 				say(&mut ears, target, Notification::Die{actor: killers.clone(), target: target_name.clone()});
-				for actor in attackers {
-					say(&mut ears, actor, Notification::Kill{actor: killers.clone(), target: target_name.clone()});
+				for actor in attackers.iter() {
+					say(&mut ears, *actor, Notification::Kill{actor: killers.clone(), target: target_name.clone()});
+				}
+				// the other players in the room hear about player kills too
+				if players.contains(target) && attackers.iter().any(|actor| players.contains(*actor)) {
+					for (bystander, _) in (&entities, &players).join() {
+						if bystander != target && !attackers.contains(&bystander) {
+							say(&mut ears, bystander, Notification::Kill{actor: killers.clone(), target: target_name.clone()});
+						}
+					}
 				}
 			}
 			if let Some(position) = positions.get(target){
@@ -6,7 +6,7 @@ use specs::{
 };
 
 use crate::{
-	resources::Ground,
+	resources::{Ground, Unsaved},
 	components::TriggerBox
 };
 
@@ -14,10 +14,12 @@ pub struct Clear;
 impl <'a> System<'a> for Clear {
 	type SystemData = (
 		Write<'a, Ground>,
+		Write<'a, Unsaved>,
 		WriteStorage<'a, TriggerBox>
 	);
-	fn run(&mut self, (mut ground, mut triggerboxes): Self::SystemData) {
+	fn run(&mut self, (mut ground, mut unsaved, mut triggerboxes): Self::SystemData) {
 		ground.changes.clear();
+		unsaved.0 = false;
 		triggerboxes.clear();
 	}
 }
@@ -1,19 +1,24 @@
-
-
 use rand::Rng;
 
 use specs::{
 	ReadStorage,
 	WriteStorage,
+	Read,
+	Write,
 	Entities,
+	Entity,
 	System,
 	Join
 };
 
 use crate::{
-	components::{Controller, ControlCooldown, Fighter, MonsterAI, Health, Position, Faction},
+	components::{Controller, ControlCooldown, Fighter, MonsterAI, BehaviourState, Route, Health, Position, Flags, Faction, Autofight, Pvp, Player, Flag, faction::FactionTable},
+	resources::{RoomFlags, Ground, Time, Unsaved},
 	controls::{Control, Direction::{self, North, South, East, West}},
-	Pos
+	behaviour::{BehaviourTable, Action, Situation},
+	pathfinding::step_towards,
+	Pos,
+	Timestamp
 };
 
 
@@ -23,66 +28,157 @@ impl <'a> System<'a> for ControlAI {
 		Entities<'a>,
 		WriteStorage<'a, Controller>,
 		ReadStorage<'a, ControlCooldown>,
-		ReadStorage<'a, MonsterAI>,
+		WriteStorage<'a, MonsterAI>,
 		ReadStorage<'a, Fighter>,
 		ReadStorage<'a, Health>,
 		ReadStorage<'a, Position>,
-		ReadStorage<'a, Faction>
+		ReadStorage<'a, Faction>,
+		Read<'a, FactionTable>,
+		WriteStorage<'a, Autofight>,
+		ReadStorage<'a, Pvp>,
+		ReadStorage<'a, Player>,
+		Read<'a, RoomFlags>,
+		WriteStorage<'a, Route>,
+		Read<'a, Ground>,
+		ReadStorage<'a, Flags>,
+		WriteStorage<'a, BehaviourState>,
+		Read<'a, BehaviourTable>,
+		Read<'a, Time>,
+		Write<'a, Unsaved>
 	);
-	fn run(&mut self, (entities, mut controllers, cooldowns, ais, fighters, healths, positions, factions): Self::SystemData) {
-	
-		for (entity, ai, position, ()) in (&entities, &ais, &positions, !&cooldowns).join() {
-			if let Some(fighter) = fighters.get(entity) {
-				let mut closest_distance = ai.view_distance + 1;
-				let mut closest = None;
-				let mut closest_position = None;
-				for (target, target_position, _) in (&entities, &positions, &healths).join() {
-					if target == entity || !Faction::is_enemy_entity(&factions, entity, target) {
-						continue;
-					}
-					let distance = position.pos.distance_to(target_position.pos);
-					if distance < closest_distance {
-						closest_distance = distance;
-						closest = Some(target);
-						closest_position = Some(target_position);
-					}
+	fn run(&mut self, (entities, mut controllers, cooldowns, mut ais, fighters, healths, positions, factions, faction_table, mut autofighters, pvps, players, room_flags, mut routes, ground, flags, mut states, behaviours, time, mut unsaved): Self::SystemData) {
+		let forced_pvp = room_flags.0.contains(&Flag::Pvp);
+		let passable = |pos: Pos| ground.is_passable(pos, &flags);
+
+		for (entity, ai, position, ()) in (&entities, &mut ais, &positions, !&cooldowns).join() {
+			let pos = position.pos;
+			// whoever attacked this entity is an enemy, even when the factions are not hostile
+			let attacker = autofighters.get_mut(entity).and_then(|autofighter| {
+				if !autofighter.target.is_some_and(|target| entities.is_alive(target)) {
+					autofighter.target = None;
 				}
-				if let Some(target) = closest {
-					if closest_distance <= fighter.range {
-						controllers.insert(entity, Controller{control: Control::AttackTarget(target)}).unwrap();
-					} else {
-						if let Some(direction) = step_to(position.pos, closest_position.unwrap().pos){
-							controllers.insert(entity, Controller{control: Control::Move(direction)}).unwrap();
-						}
-					}
-					return;
+				autofighter.target
+			});
+			let mut enemies: Vec<(Entity, Pos)> = Vec::new();
+			for (target, target_position, _) in (&entities, &positions, &healths).join() {
+				let enemy = Faction::is_enemy_entity(&factions, &faction_table, entity, target)
+					|| Pvp::allowed(&pvps, &players, forced_pvp, entity, target);
+				if target == entity || (Some(target) != attacker && !enemy) {
+					continue;
+				}
+				if pos.distance_to(target_position.pos) <= ai.view_distance {
+					enemies.push((target, target_position.pos));
 				}
 			}
-			if rand::thread_rng().gen_range(0.0, 1.0) < ai.move_chance {
-				if let Some(home) = ai.home {
-					if rand::thread_rng().gen_range(0.0, 1.0) < ai.homesickness * (position.pos.distance_to(home) as f64) {
-						let direction = step_to(position.pos, home).unwrap();
-						controllers.insert(entity, Controller{control: Control::Move(direction)}).unwrap();
-						return;
+			enemies.sort_by_key(|(_, target_pos)| pos.distance_to(*target_pos));
+			let closest = enemies.first().copied();
+
+			let behaviour = behaviours.get(ai.behaviour.as_deref());
+			let state = states.entry(entity).unwrap().or_insert_with(|| BehaviourState {
+				since: time.time,
+				waypoint: 0
+			});
+			if ai.state.as_ref().and_then(|name| behaviour.action(name)).is_none() {
+				// a new creature, or the behaviour was changed or replaced
+				ai.state = Some(behaviour.start.clone());
+				state.since = time.time;
+			}
+			let home = ai.home;
+			let anchor = *ai.anchor.get_or_insert(home.unwrap_or(pos));
+			let situation = Situation {
+				health: healths.get(entity).map(|health| (health.health, health.maxhealth)),
+				enemy_distance: closest.map(|(_, target_pos)| pos.distance_to(target_pos)),
+				anchor_distance: pos.distance_to(anchor),
+				time_in_state: time.time - state.since,
+				room_flags: &room_flags.0
+			};
+			let current = ai.state.as_deref().unwrap();
+			if let Some(next) = behaviour.transition(current, &situation) {
+				ai.state = Some(next.to_string());
+				state.since = time.time;
+				unsaved.0 = true;
+			}
+			let route = routes.entry(entity).unwrap().or_insert_with(Route::default);
+			let fighter = fighters.get(entity);
+			let control = match behaviour.action(ai.state.as_deref().unwrap()).unwrap() {
+				Action::Idle => None,
+				Action::Wander => wander(ai, pos, route, time.time, passable),
+				Action::Chase => match (fighter, closest) {
+					(Some(fighter), Some((target, target_pos))) => chase(fighter, pos, target, target_pos, route, time.time, passable),
+					// creatures that can't fight or have nothing to chase wander instead
+					_ => wander(ai, pos, route, time.time, passable)
+				},
+				Action::Flee => closest.and_then(|(_, target_pos)| flee(pos, target_pos, passable)),
+				Action::Patrol(waypoints) => {
+					let mut waypoint = anchor + waypoints[state.waypoint % waypoints.len()];
+					let mut direction = step_towards(route, pos, waypoint, time.time, passable);
+					if direction.is_none() {
+						// arrived at the waypoint, or it can't be reached
+						state.waypoint = (state.waypoint + 1) % waypoints.len();
+						waypoint = anchor + waypoints[state.waypoint];
+						direction = step_towards(route, pos, waypoint, time.time, passable);
 					}
+					direction.map(Control::Move)
 				}
-				let direction = [North, South, East, West][rand::thread_rng().gen_range(0, 4)];
-				controllers.insert(entity, Controller{control: Control::Move(direction)}).unwrap();
+				Action::Guard(radius) => {
+					let intruder = enemies.iter().find(|(_, target_pos)| target_pos.distance_to(anchor) <= *radius);
+					match (fighter, intruder) {
+						(Some(fighter), Some((target, target_pos))) => chase(fighter, pos, *target, *target_pos, route, time.time, passable),
+						_ => step_towards(route, pos, anchor, time.time, passable).map(Control::Move)
+					}
+				}
+			};
+			if let Some(control) = control {
+				controllers.insert(entity, Controller{control}).unwrap();
 			}
 		}
 	}
 }
 
-fn step_to(p: Pos, t: Pos) -> Option<Direction> {
-	let mut directions = Vec::new();
-	if t.x > p.x {directions.push(East);}
-	else if t.x < p.x {directions.push(West);}
-	if t.y > p.y {directions.push(South);}
-	else if t.y < p.y {directions.push(North);}
-	if directions.is_empty() {
-		None
+/// Attack the target when it is next to this creature, shoot when it is in a line and in range, and otherwise walk towards it
+fn chase(fighter: &Fighter, pos: Pos, target: Entity, target_pos: Pos, route: &mut Route, now: Timestamp, passable: impl Fn(Pos) -> bool) -> Option<Control> {
+	let distance = pos.distance_to(target_pos);
+	if distance <= 1 {
+		Some(Control::AttackTarget(target))
+	} else if let Some(direction) = line_to(pos, target_pos).filter(|_| distance <= fighter.range) {
+		Some(Control::Shoot(direction))
 	} else {
-		Some(directions[rand::thread_rng().gen_range(0, directions.len())])
+		step_towards(route, pos, target_pos, now, passable).map(Control::Move)
 	}
 }
 
+fn wander(ai: &MonsterAI, pos: Pos, route: &mut Route, now: Timestamp, passable: impl Fn(Pos) -> bool) -> Option<Control> {
+	if rand::thread_rng().gen_range(0.0, 1.0) >= ai.move_chance {
+		return None;
+	}
+	if let Some(home) = ai.home {
+		if rand::thread_rng().gen_range(0.0, 1.0) < ai.homesickness * (pos.distance_to(home) as f64) {
+			if let Some(direction) = step_towards(route, pos, home, now, passable) {
+				return Some(Control::Move(direction));
+			}
+		}
+	}
+	Some(Control::Move([North, South, East, West][rand::thread_rng().gen_range(0, 4)]))
+}
+
+/// Step to the free neighbouring tile that is furthest from the enemy
+fn flee(pos: Pos, enemy_pos: Pos, passable: impl Fn(Pos) -> bool) -> Option<Control> {
+	[North, South, East, West].iter()
+		.copied()
+		.filter(|direction| passable(pos + direction.to_position()))
+		.map(|direction| (direction, (pos + direction.to_position()).distance_to(enemy_pos)))
+		.filter(|(_, distance)| *distance > pos.distance_to(enemy_pos))
+		.max_by_key(|(_, distance)| *distance)
+		.map(|(direction, _)| Control::Move(direction))
+}
+
+/// The direction to shoot in when the target is in a straight line
+fn line_to(p: Pos, t: Pos) -> Option<Direction> {
+	if p.x == t.x && p.y != t.y {
+		Some(if t.y > p.y {South} else {North})
+	} else if p.y == t.y && p.x != t.x {
+		Some(if t.x > p.x {East} else {West})
+	} else {
+		None
+	}
+}
@@ -9,7 +9,7 @@ use specs::{
 };
 
 use crate::{
-	components::{Controller, Player, ControlCooldown, Autofight},
+	components::{Controller, Player, ControlCooldown, Autofight, Pvp, Ear, Notification, say},
 	resources::{Input},
 	controls::Control
 };
@@ -23,22 +23,39 @@ impl <'a> System<'a> for ControlInput {
 		WriteStorage<'a, Controller>,
 		ReadStorage<'a, Player>,
 		ReadStorage<'a, ControlCooldown>,
-		WriteStorage<'a, Autofight>
+		WriteStorage<'a, Autofight>,
+		WriteStorage<'a, Pvp>,
+		WriteStorage<'a, Ear>
 	);
-	fn run(&mut self, (entities, mut input, mut controllers, players, cooldowns, mut autofighters): Self::SystemData) {
+	fn run(&mut self, (entities, mut input, mut controllers, players, cooldowns, mut autofighters, mut pvps, mut ears): Self::SystemData) {
 	
 		for (player, entity, ()) in (&players, &entities, !&cooldowns).join() {
-			if let Some(control) = input.actions.remove(&player.id){
-				controllers.insert(entity, Controller{control: control}).unwrap();
-				if let Some(autofighter) = autofighters.get_mut(entity) {
-					autofighter.target = None;
+			match input.actions.remove(&player.id) {
+				Some(Control::Pvp(enabled)) => {
+					let text = if enabled {
+						pvps.insert(entity, Pvp).unwrap();
+						"You can now fight other players who also enabled pvp"
+					} else {
+						pvps.remove(entity);
+						"You no longer fight other players"
+					};
+					say(&mut ears, entity, Notification::Sound{source: None, text: text.to_string()});
 				}
-			} else if let Some(autofighter) = autofighters.get_mut(entity) {
-				if let Some(target) = autofighter.target {
-					if !entities.is_alive(target) {
+				Some(control) => {
+					controllers.insert(entity, Controller{control: control}).unwrap();
+					if let Some(autofighter) = autofighters.get_mut(entity) {
 						autofighter.target = None;
-					} else {
-						controllers.insert(entity, Controller{control: Control::AttackTarget(target)}).unwrap();
+					}
+				}
+				None => {
+					if let Some(autofighter) = autofighters.get_mut(entity) {
+						if let Some(target) = autofighter.target {
+							if !entities.is_alive(target) {
+								autofighter.target = None;
+							} else {
+								controllers.insert(entity, Controller{control: Control::AttackTarget(target)}).unwrap();
+							}
+						}
 					}
 				}
 			}
@@ -1,5 +1,5 @@
-
 use specs::{
+	Read,
 	Write,
 	WriteStorage,
 	ReadStorage,
@@ -9,10 +9,14 @@ use specs::{
 };
 
 use crate::{
-	components::{Trigger, TriggerBox, Removed, Player},
-	resources::Emigration,
+	components::{Trigger, TriggerBox, Removed, Player, Inventory, Position},
+	resources::{Emigration, NewEntities, Time},
+	deathpenalty::DeathPenalty,
+	fromtoparameter::FromToParameter,
 	purgatory,
-	playerstate::RoomPos
+	playerstate::RoomPos,
+	Template,
+	hashmap
 };
 
 
@@ -23,13 +27,36 @@ impl <'a> System<'a> for Die {
 		ReadStorage<'a, TriggerBox>,
 		WriteStorage<'a, Removed>,
 		Write<'a, Emigration>,
-		ReadStorage<'a, Player>
+		ReadStorage<'a, Player>,
+		WriteStorage<'a, Inventory>,
+		ReadStorage<'a, Position>,
+		Read<'a, DeathPenalty>,
+		Read<'a, Time>,
+		Write<'a, NewEntities>
 	);
-	fn run(&mut self, (entities, triggerboxes, mut removeds, mut emigration, players): Self::SystemData) {
+	fn run(&mut self, (entities, triggerboxes, mut removeds, mut emigration, players, mut inventories, positions, death_penalty, time, mut new): Self::SystemData) {
 		for (entity, triggerbox) in (&entities, &triggerboxes).join() {
 			if triggerbox.has_message(&[Trigger::Die, Trigger::Remove, Trigger::Change]){
 				if let Some(player) = players.get(entity) {
-					// players move to purgatory when dead
+					// players leave their lost items in a grave and move to purgatory when dead
+					let dead = triggerbox.has_message(&[Trigger::Die]);
+					if let (true, Some(inventory), Some(position)) = (dead, inventories.get_mut(entity), positions.get(entity)) {
+						let items = death_penalty.take_items(inventory);
+						if !items.is_empty() {
+							let grave = Template::new("grave", hashmap!{
+								"owner".to_string() => player.id.clone().to_parameter(),
+								"items".to_string() => items.clone().to_parameter(),
+								"public_at".to_string() => (time.time + death_penalty.private_time).to_parameter()
+							});
+							if let Err(err) = new.create(position.pos, &grave) {
+								// without a grave the player keeps the items
+								println!("failed to create grave for {}: {:?}", player.id, err);
+								for itemid in items.into_iter().rev() {
+									inventory.add_item(itemid, &new.encyclopedia);
+								}
+							}
+						}
+					}
 					emigration.emigrants.push((player.id.clone(), purgatory::purgatory_id(), RoomPos::Unknown));
 				} else {
 					// npcs etc get removed when dead
@@ -39,4 +66,3 @@ impl <'a> System<'a> for Die {
 		}
 	}
 }
-
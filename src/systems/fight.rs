@@ -6,24 +6,34 @@ use specs::{
 	WriteStorage,
 	System,
 	Join,
-	Read
+	Read,
+	Write
 };
 
 use crate::components::{
 	Controller,
 	Position,
 	AttackInbox,
-	AttackMessage,
 	Fighter,
 	Health,
 	ControlCooldown,
 	Autofight,
 	Faction,
-	Inventory
+	faction::{FactionTable, Relation},
+	Pvp,
+	Player,
+	Flag,
+	Inventory,
+	Stats,
+	Stat,
+	Projectile,
+	StatusEffects
 };
 
-use crate::controls::{Control};
-use crate::resources::{Ground};
+use crate::controls::{Control, Direction};
+use crate::resources::{Ground, NewEntities, RoomFlags};
+use crate::componentwrapper::ComponentWrapper;
+use crate::Template;
 
 
 
@@ -40,39 +50,70 @@ impl <'a> System<'a> for Fight {
 		WriteStorage<'a, ControlCooldown>,
 		WriteStorage<'a, Autofight>,
 		ReadStorage<'a, Faction>,
-		ReadStorage<'a, Inventory>
+		Read<'a, FactionTable>,
+		ReadStorage<'a, Inventory>,
+		ReadStorage<'a, Stats>,
+		ReadStorage<'a, StatusEffects>,
+		Write<'a, NewEntities>,
+		ReadStorage<'a, Pvp>,
+		ReadStorage<'a, Player>,
+		Read<'a, RoomFlags>
 	);
 	
-	fn run(&mut self, (entities, controllers, positions, ground, mut attacked, fighters, healths, mut cooldowns, mut autofighters, factions, inventories): Self::SystemData) {
+	fn run(&mut self, (entities, controllers, positions, ground, mut attacked, fighters, healths, mut cooldowns, mut autofighters, factions, faction_table, inventories, stats, effects, mut new, pvps, players, room_flags): Self::SystemData) {
+		let forced_pvp = room_flags.0.contains(&Flag::Pvp);
+		let relation = |a, b| if Pvp::allowed(&pvps, &players, forced_pvp, a, b) {
+			Relation::Hostile
+		} else {
+			Faction::relation_entity(&factions, &faction_table, a, b)
+		};
 		for (entity, controller, position, fighter) in (&entities, &controllers, &positions, &fighters).join(){
 			let mut target = None;
 			match &controller.control {
 				Control::Attack(directions) => {
-					'targets: for direction in directions {
-						for ent in ground.cells.get(&(position.pos + direction.to_position())).unwrap_or(&HashSet::new()) {
-							if healths.contains(*ent) && *ent != entity && Faction::is_enemy_entity(&factions, entity, *ent) {
-								target = Some(*ent);
-								break 'targets;
+					// enemies are attacked first, but neutral entities can be attacked too
+					'relations: for wanted in &[Relation::Hostile, Relation::Neutral] {
+						for direction in directions {
+							for ent in ground.cells.get(&(position.pos + direction.to_position())).unwrap_or(&HashSet::new()) {
+								if healths.contains(*ent) && *ent != entity && relation(entity, *ent) == *wanted {
+									target = Some(*ent);
+									break 'relations;
+								}
 							}
 						}
 					}
 				}
 				Control::AttackTarget(t) => {
-					if *t == entity { // don't knock yourself out
+					if *t == entity || relation(entity, *t) == Relation::Friendly { // don't knock yourself out or your friends
 					} else if let Some(target_position) = positions.get(*t){
-						if position.pos.distance_to(target_position.pos) <= fighter.range {
+						if position.pos.distance_to(target_position.pos) <= 1 {
 							target = Some(*t);
 						}
 					}
 				}
+				Control::Shoot(direction) => {
+					let bonuses = Stats::total(stats.get(entity), inventories.get(entity), effects.get(entity));
+					let range = fighter.range + bonuses.get(&Stat::Range).unwrap_or(&0);
+					// only fighters with a ranged weapon or a range in their assemblage can shoot
+					if range > 1 && *direction != Direction::None {
+						let projectile = Projectile {
+							attack: fighter.attack.clone().apply_bonuses(&bonuses),
+							direction: *direction,
+							range,
+							effects: fighter.effects.clone(),
+							attacker: Some(entity)
+						};
+						if let Err(err) = new.create_with(position.pos, &Template::empty("arrow"), vec![ComponentWrapper::Projectile(projectile)]) {
+							println!("failed to create projectile: {:?}", err);
+						}
+						cooldowns.insert(entity, ControlCooldown{amount: fighter.cooldown}).unwrap();
+					}
+				}
 				_ => {}
 			}
 			if let Some(ent) = target {
-				let mut attack = fighter.attack.clone();
-				if let Some(inventory) = inventories.get(entity) {
-					attack = attack.apply_bonuses(&inventory.equipment_bonuses());
-				}
-				AttackInbox::add_message(&mut attacked, ent, AttackMessage{typ: attack, attacker: Some(entity)});
+				let attack = fighter.attack.clone().apply_bonuses(&Stats::total(stats.get(entity), inventories.get(entity), effects.get(entity)));
+				AttackInbox::add_attack(&mut attacked, ent, attack, &fighter.effects, Some(entity));
 				cooldowns.insert(entity, ControlCooldown{amount: fighter.cooldown}).unwrap();
 				if let Some(autofighter) = autofighters.get_mut(entity){
 					autofighter.target = Some(ent);
@@ -0,0 +1,83 @@
+
+use specs::{
+	ReadStorage,
+	WriteStorage,
+	Write,
+	Read,
+	Entities,
+	System,
+	Join
+};
+
+use crate::{
+	components::{
+		Projectile,
+		Position,
+		Health,
+		Flags,
+		Flag,
+		Faction,
+		faction::FactionTable,
+		Pvp,
+		Player,
+		Removed,
+		AttackInbox
+	},
+	resources::{Ground, RoomFlags}
+};
+
+
+pub struct Fly;
+impl <'a> System<'a> for Fly {
+	type SystemData = (
+		Entities<'a>,
+		WriteStorage<'a, Projectile>,
+		WriteStorage<'a, Position>,
+		Write<'a, Ground>,
+		ReadStorage<'a, Flags>,
+		ReadStorage<'a, Health>,
+		ReadStorage<'a, Faction>,
+		Read<'a, FactionTable>,
+		WriteStorage<'a, Removed>,
+		WriteStorage<'a, AttackInbox>,
+		ReadStorage<'a, Pvp>,
+		ReadStorage<'a, Player>,
+		Read<'a, RoomFlags>
+	);
+	fn run(&mut self, (entities, mut projectiles, mut positions, mut ground, flags, healths, factions, faction_table, mut removeds, mut attacked, pvps, players, room_flags): Self::SystemData) {
+		let forced_pvp = room_flags.0.contains(&Flag::Pvp);
+		for (entity, projectile, position) in (&entities, &mut projectiles, &mut positions).join() {
+			if removeds.contains(entity) {
+				continue;
+			}
+			if projectile.range <= 0 {
+				removeds.insert(entity, Removed).unwrap();
+				continue;
+			}
+			projectile.range -= 1;
+			let newpos = position.pos + projectile.direction.to_position();
+			// projectiles fly over the shooter and its allies
+			let target = ground.cells.get(&newpos).and_then(|cell| cell.iter().find(|ent|
+				healths.contains(**ent) && projectile.attacker.map_or(true, |attacker|
+					attacker != **ent && (
+						Pvp::allowed(&pvps, &players, forced_pvp, attacker, **ent)
+						|| !Faction::is_ally_entity(&factions, &faction_table, attacker, **ent)
+					)
+				)
+			).copied());
+			if let Some(target) = target {
+				AttackInbox::add_attack(&mut attacked, target, projectile.attack.clone(), &projectile.effects, projectile.attacker);
+				removeds.insert(entity, Removed).unwrap();
+				continue;
+			}
+			let ground_flags = ground.flags_on(newpos, &flags);
+			if ground_flags.contains(&Flag::Blocking) || !ground_flags.contains(&Flag::Floor) {
+				removeds.insert(entity, Removed).unwrap();
+				continue;
+			}
+			ground.remove(&position.pos, entity);
+			position.pos = newpos;
+			ground.insert(newpos, entity);
+		}
+	}
+}
@@ -1,5 +1,5 @@
 
-use std::collections::{HashSet, HashMap};
+use std::collections::HashSet;
 use rand::Rng;
 
 use specs::{
@@ -19,6 +19,7 @@ use crate::{
 		ControlCooldown,
 		Interactable,
 		TriggerBox,
+		Trigger,
 		Notification,
 		Ear,
 		Inventory,
@@ -26,16 +27,25 @@ use crate::{
 		Player,
 		Whitelist,
 		Minable,
-		Stats
+		Stats,
+		StatusEffects,
+		Stat,
+		Loot,
+		Grave,
+		RespawnPoint
 	},
 	controls::{Control},
-	resources::{Ground, Emigration, NewEntities},
+	resources::{Ground, Emigration, NewEntities, Time, Unsaved},
 	hashmap,
 	playerstate::RoomPos,
 	PlayerId,
+	RoomId,
 	util::strip_prefix
 };
 
+/// Experience for finishing a harvest or mining something
+const HARVEST_EXPERIENCE: i64 = 5;
+
 pub struct Interact;
 impl <'a> System<'a> for Interact {
 	type SystemData = (
@@ -43,6 +53,7 @@ impl <'a> System<'a> for Interact {
 		ReadStorage<'a, Controller>,
 		ReadStorage<'a, Position>,
 		Read<'a, Ground>,
+		Write<'a, Unsaved>,
 		WriteStorage<'a, ControlCooldown>,
 		ReadStorage<'a, Interactable>,
 		WriteStorage<'a, TriggerBox>,
@@ -53,11 +64,17 @@ impl <'a> System<'a> for Interact {
 		Write<'a, Emigration>,
 		WriteStorage<'a, Whitelist>,
 		WriteStorage<'a, Minable>,
-		ReadStorage<'a, Stats>,
-		Read<'a, NewEntities>
+		WriteStorage<'a, Stats>,
+		ReadStorage<'a, StatusEffects>,
+		Read<'a, NewEntities>,
+		ReadStorage<'a, Loot>,
+		WriteStorage<'a, Grave>,
+		Read<'a, Time>,
+		WriteStorage<'a, RespawnPoint>,
+		Read<'a, RoomId>
 	);
 	
-	fn run(&mut self, (entities, controllers, positions, ground, mut cooldowns, interactables, mut triggerbox, mut ears, mut inventories, visibles, players, mut emigration, mut whitelists, mut minables, stats, new): Self::SystemData) {
+	fn run(&mut self, (entities, controllers, positions, ground, mut unsaved, mut cooldowns, interactables, mut triggerbox, mut ears, mut inventories, visibles, players, mut emigration, mut whitelists, mut minables, mut stats, effects, new, loots, mut graves, time, mut respawn_points, room_id): Self::SystemData) {
 		for (actor, controller, position) in (&entities, &controllers, &positions).join(){
 			let mut target = None;
 			let ear = ears.get_mut(actor);
@@ -81,6 +98,9 @@ impl <'a> System<'a> for Interact {
 					}
 					Interactable::Trigger(trigger) => {
 						TriggerBox::add_message(&mut triggerbox, ent, *trigger);
+						if loots.contains(ent) && (*trigger == Trigger::Die || *trigger == Trigger::Loot) {
+							Stats::train(&mut stats, &mut ears, actor, Stat::Gathering, HARVEST_EXPERIENCE);
+						}
 					}
 					Interactable::Visit(dest) => {
 						if let Some(argument) = arg {
@@ -99,10 +119,12 @@ impl <'a> System<'a> for Interact {
 								} else if let Some(playername) = strip_prefix(&argument, "allow ") {
 									let destination = dest.format(hashmap!("{player}" => player.id.0.as_str()));
 									whitelist.allowed.entry(destination.0).or_insert_with(HashSet::new).insert(PlayerId( playername.to_string()));
+									unsaved.0 = true;
 									say(ear, format!("allowed {} to enter your home", playername), name);
 								} else if let Some(playername) = strip_prefix(&argument, "disallow ") {
 									let destination = dest.format(hashmap!("{player}" => player.id.0.as_str()));
 									whitelist.allowed.entry(destination.0).or_insert_with(HashSet::new).remove(&PlayerId( playername.to_string()));
+									unsaved.0 = true;
 									say(ear, format!("disallowed {} to enter your home", playername), name);
 								} else if argument.starts_with("whitelist") {
 									let destination = dest.format(hashmap!("{player}" => player.id.0.as_str()));
@@ -124,24 +146,50 @@ impl <'a> System<'a> for Interact {
 					}
 					Interactable::Mine(skill) => {
 						if let Some(minable) = minables.get_mut(ent) {
-							let mut skills = inventories.get(actor).map(Inventory::equipment_bonuses).unwrap_or_else(HashMap::new);
-							if let Some(skillset) = stats.get(actor) {
-								for (skill, val) in skillset.skills.iter() {
-									*skills.entry(*skill).or_insert(0) += val;
-								}
-							}
+							let skills = Stats::total(stats.get(actor), inventories.get(actor), effects.get(actor));
 							if let Some(skill_value) = skills.get(skill) {
 								println!("{:?} {:?}", skill, skill_value);
 								// todo: give player feedback
 								cooldown = 20;
 								minable.progress += rand::thread_rng().gen_range(0, skill_value+1);
+								unsaved.0 = true;
+								let mut experience = 1;
 								if minable.progress >= minable.total {
 									TriggerBox::add_message(&mut triggerbox, ent, minable.trigger);
 									minable.progress = 0;
+									experience += HARVEST_EXPERIENCE;
 								}
+								Stats::train(&mut stats, &mut ears, actor, *skill, experience);
 							}
 						}
 					}
+					Interactable::Grave => {
+						if let (Some(grave), Some(player), Some(inventory)) = (graves.get_mut(ent), players.get(actor), inventories.get_mut(actor)) {
+							if player.id != grave.owner && time.time < grave.public_at {
+								say(ear, format!("Only {} can take the items from this grave for now", grave.owner), name);
+							} else {
+								while inventory.items.len() < inventory.capacity && !grave.items.is_empty() {
+									let itemid = grave.items.pop().unwrap();
+									// items that were removed from the content are lost
+									if new.encyclopedia.get_item(&itemid).is_some() {
+										inventory.add_item(itemid, &new.encyclopedia);
+									}
+								}
+								if grave.items.is_empty() {
+									TriggerBox::add_message(&mut triggerbox, ent, Trigger::Remove);
+								} else {
+									say(ear, format!("Your inventory is full, {} items are left in the grave", grave.items.len()), name);
+								}
+								unsaved.0 = true;
+							}
+						}
+					}
+					Interactable::Bind => {
+						if players.contains(actor) {
+							respawn_points.insert(actor, RespawnPoint{room: room_id.clone(), pos: position.pos}).unwrap();
+							say(ear, "You will respawn here when you die".to_string(), name);
+						}
+					}
 					Interactable::Exchange(prefix, exchanges) => {
 						if let Some(txt) = arg {
 							if let (Some(inventory), Some(action)) = (inventories.get_mut(actor), strip_prefix(&txt, prefix)) {
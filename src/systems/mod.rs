@@ -11,8 +11,10 @@ pub mod useitem;
 pub mod attacking;
 pub mod trapping;
 pub mod fight;
+pub mod fly;
 pub mod heal;
 pub mod updatecooldowns;
+pub mod updateeffects;
 pub mod controlai;
 pub mod die;
 pub mod spawn;
@@ -25,4 +27,5 @@ pub mod spawntrigger;
 pub mod replace;
 pub mod spawncheck;
 pub mod describe;
+pub mod respawn;
 
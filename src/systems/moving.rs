@@ -5,6 +5,7 @@ use specs::{
 	WriteStorage,
 	System,
 	Join,
+	Read,
 	Write
 };
 
@@ -13,18 +14,24 @@ use crate::{
 		Controller,
 		Position,
 		Flags,
-		Flag,
 		Moved,
 		Entered,
 		Movable,
-		ControlCooldown
+		ControlCooldown,
+		Player,
+		StatusEffects
 	},
 	controls::{
 		Control
 	},
 	resources::{
-		Ground
+		Ground,
+		Size,
+		Neighbours,
+		Emigration
 	},
+	playerstate::RoomPos,
+	Pos
 };
 
 
@@ -39,17 +46,34 @@ impl <'a> System<'a> for Move {
 		WriteStorage<'a, Moved>,
 		WriteStorage<'a, Entered>,
 		ReadStorage<'a, Movable>,
-		WriteStorage<'a, ControlCooldown>
+		WriteStorage<'a, ControlCooldown>,
+		ReadStorage<'a, Player>,
+		Read<'a, Size>,
+		Read<'a, Neighbours>,
+		Write<'a, Emigration>,
+		ReadStorage<'a, StatusEffects>
 	);
 	
-	fn run(&mut self, (entities, controllers, mut positions, flags, mut ground, mut moved, mut entered, movables, mut cooldowns): Self::SystemData) {
+	fn run(&mut self, (entities, controllers, mut positions, flags, mut ground, mut moved, mut entered, movables, mut cooldowns, players, size, neighbours, mut emigration, statuses): Self::SystemData) {
 		moved.clear();
 		entered.clear();
 		for (ent, controller, mut position, movable) in (&entities, &controllers, &mut positions, &movables).join(){
 			if let Control::Move(direction) = &controller.control {
+				let cooldown = movable.cooldown + statuses.get(ent).map_or(0, StatusEffects::slow);
 				let newpos = position.pos + direction.to_position();
-				let ground_flags = ground.flags_on(newpos, &flags);
-				if !ground_flags.contains(&Flag::Blocking) && ground_flags.contains(&Flag::Floor) {
+				if let (Some(player), Some(destination)) = (players.get(ent), neighbours.0.get(direction)) {
+					if newpos.x < 0 || newpos.y < 0 || newpos.x >= size.width || newpos.y >= size.height {
+						// walking off the right or bottom edge enters at coordinate 0, walking off the left or top edge enters at -1, which is the other edge of the destination
+						let destpos = Pos::new(
+							if newpos.x >= size.width {0} else {newpos.x},
+							if newpos.y >= size.height {0} else {newpos.y}
+						);
+						emigration.emigrants.push((player.id.clone(), destination.clone(), RoomPos::Pos(destpos)));
+						cooldowns.insert(ent, ControlCooldown{amount: cooldown}).unwrap();
+						continue;
+					}
+				}
+				if ground.is_passable(newpos, &flags) {
 					moved.insert(ent, Moved{from: position.pos}).expect("can't insert Moved");
 					ground.remove(&position.pos, ent);
 					position.pos = newpos;
@@ -57,7 +81,7 @@ impl <'a> System<'a> for Move {
 					for ent in ground.cells.get(&newpos).unwrap() {
 						let _ = entered.insert(*ent, Entered);
 					}
-					cooldowns.insert(ent, ControlCooldown{amount: movable.cooldown}).unwrap();
+					cooldowns.insert(ent, ControlCooldown{amount: cooldown}).unwrap();
 				}
 			}
 		}
@@ -0,0 +1,29 @@
+use specs::{
+	ReadStorage,
+	Write,
+	System,
+	Join
+};
+
+use crate::{
+	components::{Controller, Player},
+	controls::Control,
+	resources::Emigration
+};
+
+/// Dead players in the purgatory go back to their respawn point
+pub struct Respawn;
+impl <'a> System<'a> for Respawn {
+	type SystemData = (
+		ReadStorage<'a, Controller>,
+		ReadStorage<'a, Player>,
+		Write<'a, Emigration>
+	);
+	fn run(&mut self, (controllers, players, mut emigration): Self::SystemData) {
+		for (controller, player) in (&controllers, &players).join() {
+			if let Control::Respawn = controller.control {
+				emigration.respawns.push(player.id.clone());
+			}
+		}
+	}
+}
@@ -6,6 +6,7 @@ use rand::Rng;
 use specs::{
 	WriteStorage,
 	ReadStorage,
+	Read,
 	Write,
 	System,
 	Join,
@@ -21,7 +22,7 @@ use crate::{
 		Trigger,
 		TimeOffset
 	},
-	resources::{NewEntities},
+	resources::{NewEntities, RoomFlags, Time},
 	componentwrapper::ComponentWrapper,
 	Pos,
 	fromtoparameter::FromToParameter
@@ -38,10 +39,12 @@ impl <'a> System<'a> for Spawn {
 		WriteStorage<'a, Spawner>,
 		ReadStorage<'a, Clan>,
 		ReadStorage<'a, TriggerBox>,
-		WriteStorage<'a, TimeOffset>
+		WriteStorage<'a, TimeOffset>,
+		Read<'a, RoomFlags>,
+		Read<'a, Time>
 	);
 	
-	fn run(&mut self, (entities, positions, mut new, mut spawners, clans, triggerboxes, mut timeoffsets): Self::SystemData) {
+	fn run(&mut self, (entities, positions, mut new, mut spawners, clans, triggerboxes, mut timeoffsets, roomflags, time): Self::SystemData) {
 		let mut clan_nums: HashMap<&Clan, usize> = HashMap::new();
 		for clan in (&clans).join() {
 			let n: usize = *clan_nums.entry(clan).or_insert(0);
@@ -52,13 +55,14 @@ impl <'a> System<'a> for Spawn {
 			if spawner.clan.name == "" {
 				spawner.clan.name = format!("$random({},{},{})", position.pos.x, position.pos.y, spawner.template.name.0);
 			}
-			spawner.template.kwargs.insert("home".to_string(), Some(position.pos).to_parameter());
-			if triggerbox.has_message(&[Trigger::Spawn]) {
+			let mut template = spawner.season_templates.get(&time.season()).unwrap_or(&spawner.template).clone();
+			template.kwargs.insert("home".to_string(), Some(position.pos).to_parameter());
+			if triggerbox.has_message(&[Trigger::Spawn]) && spawner.required_flags.is_subset(&roomflags.0) {
 				if *clan_nums.get(&spawner.clan).unwrap_or(&0) < spawner.amount {
 					if spawner.saturated {
 						spawner.saturated = false;
 					} else {
-						match new.encyclopedia.construct(&spawner.template) {
+						match new.encyclopedia.construct(&template) {
 							Ok(mut preent) => {
 								preent.push(ComponentWrapper::Clan(spawner.clan.clone()));
 								let offset = Pos::new(
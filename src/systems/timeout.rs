@@ -37,7 +37,8 @@ impl <'a> System<'a> for Timeout {
 				if rand::random() {
 					r = 1.0 / r;
 				}
-				let duration = (timer.delay as f64 * r + 0.4) as i64;
+				let factor = timer.season_factors.get(&time.clock.season(creation_time)).cloned().unwrap_or(1.0);
+				let duration = (timer.delay as f64 * factor * r + 0.4) as i64;
 				timer.target_time = Some(creation_time + duration);
 			}
 			let target_time = timer.target_time.unwrap();
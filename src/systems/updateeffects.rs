@@ -0,0 +1,44 @@
+
+use specs::{
+	WriteStorage,
+	Read,
+	Entities,
+	System,
+	Join
+};
+
+use crate::{
+	components::{StatusEffects, AttackInbox, AttackMessage, AttackType},
+	resources::Time
+};
+
+
+pub struct UpdateEffects;
+impl <'a> System<'a> for UpdateEffects {
+	type SystemData = (
+		Entities<'a>,
+		WriteStorage<'a, StatusEffects>,
+		WriteStorage<'a, AttackInbox>,
+		Read<'a, Time>
+	);
+	fn run(&mut self, (entities, mut statuses, mut attacked, time): Self::SystemData) {
+		let mut to_remove = Vec::new();
+		for (entity, status) in (&entities, &mut statuses).join() {
+			status.expire(time.time);
+			if status.effects.is_empty() {
+				to_remove.push(entity);
+				continue;
+			}
+			let (poison, regeneration) = status.health_change(time.time);
+			if poison > 0 {
+				AttackInbox::add_message(&mut attacked, entity, AttackMessage{typ: AttackType::Poison(poison), attacker: None});
+			}
+			if regeneration > 0 {
+				AttackInbox::add_message(&mut attacked, entity, AttackMessage{typ: AttackType::Heal(regeneration), attacker: None});
+			}
+		}
+		for entity in to_remove {
+			statuses.remove(entity);
+		}
+	}
+}
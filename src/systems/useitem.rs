@@ -54,8 +54,13 @@ impl <'a> System<'a> for Use {
 								inventory.items.remove(*rank);
 							}
 						}
-						Eat(health_diff) => {
-							AttackInbox::add_message(&mut attacked, ent, AttackMessage{typ: AttackType::Heal(*health_diff), attacker: Option::None});
+						Eat(food) => {
+							if food.health != 0 {
+								AttackInbox::add_message(&mut attacked, ent, AttackMessage{typ: AttackType::Heal(food.health), attacker: Option::None});
+							}
+							for effect in food.effects.iter() {
+								AttackInbox::add_message(&mut attacked, ent, AttackMessage{typ: AttackType::Effect(*effect), attacker: Option::None});
+							}
 							inventory.items.remove(*rank);
 						}
 						Equip(equippable) => {
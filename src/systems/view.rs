@@ -13,9 +13,9 @@ use specs::{
 use crate::{
 	Pos,
 	Sprite,
-	components::{Visible, Player, Position, Inventory, New, Health, Ear},
-	resources::{Size, Output, Ground},
-	worldmessages::{WorldMessage, FieldMessage}
+	components::{Visible, Player, Position, Inventory, New, Health, Ear, StatusEffects, effect::EFFECT_INTERVAL},
+	resources::{Size, Output, Ground, Time},
+	worldmessages::{WorldMessage, FieldMessage, ClockMessage}
 };
 
 pub struct View;
@@ -32,9 +32,18 @@ impl <'a> System<'a> for View {
 		Write<'a, Output>,
 		ReadStorage<'a, New>,
 		Read<'a, Ground>,
-		WriteStorage<'a, Ear>
+		WriteStorage<'a, Ear>,
+		Read<'a, Time>,
+		ReadStorage<'a, StatusEffects>
 	);
-	fn run(&mut self, (entities, positions, inventories, healths, visible, size, players, mut output, new, ground, mut ears): Self::SystemData) {
+	fn run(&mut self, (entities, positions, inventories, healths, visible, size, players, mut output, new, ground, mut ears, time, statuses): Self::SystemData) {
+		
+		let clock = ClockMessage {
+			day: time.clock.day(time.time),
+			time: (time.clock.time_of_day(time.time) * 100.0).floor() / 100.0,
+			night: time.is_night(),
+			season: time.season().to_string()
+		};
 		
 		let changes: Vec<(Pos, Vec<Sprite>)> = ground.changes
 			.iter()
@@ -77,7 +86,14 @@ impl <'a> System<'a> for View {
 					.map(|ent| visible.get(ent).unwrap().name.clone())
 					.collect()
 			);
+			updates.effects = Some(statuses.get(ent).map(|status|
+				status.effects.iter().map(|active| {
+					let remaining = active.until - time.time;
+					(active.effect.kind.to_string(), active.effect.amount, (remaining + EFFECT_INTERVAL - 1) / EFFECT_INTERVAL * EFFECT_INTERVAL)
+				}).collect()
+			).unwrap_or_default());
 			updates.pos = Some(pos.pos);
+			updates.clock = Some(clock.clone());
 			if !updates.is_empty() {
 				output.output.insert(player.id.clone(), updates);
 			}
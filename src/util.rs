@@ -37,6 +37,25 @@ pub fn write_file_safe<P: AsRef<Path>, C: AsRef<[u8]>>(path: P, contents: C) ->
 	Ok(())
 }
 
+/// The names (without the suffix) of all files in a directory that end with the suffix.
+/// A directory that does not exist is treated as empty.
+pub fn list_names_with_suffix<P: AsRef<Path>>(path: P, suffix: &str) -> Result<Vec<String>, AnyError> {
+	let entries = match fs::read_dir(path) {
+		Ok(entries) => entries,
+		Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+		Err(err) => return Err(Box::new(err))
+	};
+	let mut names = Vec::new();
+	for entry in entries {
+		if let Some(fname) = entry?.file_name().to_str() {
+			if let Some(name) = fname.strip_suffix(suffix) {
+				names.push(name.to_string());
+			}
+		}
+	}
+	Ok(names)
+}
+
 
 #[macro_export]
 macro_rules! hashmap {
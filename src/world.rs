@@ -1,5 +1,6 @@
 
 use std::collections::HashMap;
+use rayon::prelude::*;
 
 use crate::{
 	PlayerId,
@@ -15,6 +16,8 @@ use crate::{
 	aerr,
 	worldmessages::WorldMessage,
 	Timestamp,
+	clock::Clock,
+	deathpenalty::DeathPenalty,
 	purgatory
 };
 
@@ -25,8 +28,13 @@ pub struct World {
 	players: HashMap<PlayerId, RoomId>,
 	rooms: HashMap<RoomId, Room>,
 	room_age: HashMap<RoomId, Timestamp>,
+	saved_players: HashMap<PlayerId, PlayerState>,
 	encyclopedia: Encyclopedia,
-	pub time: Timestamp
+	clock: Clock,
+	pub time: Timestamp,
+	/// The maximum number of steps that are simulated to bring a reloaded room up to date
+	pub fast_forward_steps: i64,
+	pub death_penalty: DeathPenalty
 }
 
 #[derive(Debug)]
@@ -37,7 +45,7 @@ pub enum MigrationError {
 
 impl World {
 	
-	pub fn new(encyclopedia: Encyclopedia, template_loader: WorldLoader, persistence: Box<dyn PersistentStorage>, default_room: RoomId) -> Self {
+	pub fn new(encyclopedia: Encyclopedia, template_loader: WorldLoader, persistence: Box<dyn PersistentStorage>, default_room: RoomId, clock: Clock) -> Self {
 		let time = match persistence.load_world_meta() {
 			Ok(time) => {time}
 			Err(LoaderError::MissingResource(_)) => {
@@ -53,9 +61,13 @@ impl World {
 			persistence,
 			default_room,
 			encyclopedia: encyclopedia,
+			clock,
 			players: HashMap::new(),
 			rooms: HashMap::new(),
-			room_age: HashMap::new()
+			room_age: HashMap::new(),
+			saved_players: HashMap::new(),
+			fast_forward_steps: 100,
+			death_penalty: DeathPenalty::default()
 		}
 	}
 	
@@ -79,16 +91,22 @@ impl World {
 					room.load_from_template(&template)?;
 					room
 				};
-			match self.persistence.load_room(id.clone()){
+			room.set_clock(self.clock);
+			room.set_death_penalty(self.death_penalty);
+			let saved_time = match self.persistence.load_room(id.clone()){
 				Ok(state) => {
 					room.load_saved(&state);
+					state.time
 				}
-				Err(LoaderError::MissingResource(_)) => {}
+				Err(LoaderError::MissingResource(_)) => None,
 				Err(LoaderError::InvalidResource(err)) => {return Err(err);}
-			}
+			};
 			let last_time = self.time - 1;
-			if room.get_time() < last_time {
-				room.update(last_time);
+			match saved_time {
+				Some(time) if time < last_time && self.fast_forward_steps > 0 => room.fast_forward(time, last_time, self.fast_forward_steps),
+				_ => if room.get_time() < last_time {
+					room.update(last_time);
+				}
 			}
 			self.rooms.insert(id.clone(), room);
 		}
@@ -149,6 +167,7 @@ impl World {
 	
 	pub fn remove_player(&mut self, playerid: &PlayerId) -> Result<()> {
 		let player_state = self.discorporate_player(playerid)?;
+		self.saved_players.remove(playerid);
 		self.persistence.save_player(playerid.clone(), player_state)?;
 		Ok(())
 	}
@@ -161,6 +180,12 @@ impl World {
 	}
 	
 	fn migrate_player(&mut self, player: &PlayerId, destination: RoomId, roompos: RoomPos) -> Result<()> {
+		if let RoomPos::Pos(pos) = roompos {
+			// players that walk off an edge stay where they are when the tile on the other side is blocked
+			if self.get_room_mut(&destination).is_ok_and(|room| !room.can_enter(pos)) {
+				return Ok(());
+			}
+		}
 		let mut state = self.discorporate_player(player)?;
 		let old_room = state.room;
 		state.room = Some(destination);
@@ -169,32 +194,71 @@ impl World {
 	}
 	
 	
+	fn respawn_player(&mut self, player: &PlayerId) -> Result<()> {
+		let state = self.discorporate_player(player)?;
+		let mut respawned = state.clone();
+		respawned.respawn();
+		if let Err(err) = self.try_add_loaded_player(respawned, &[None]) {
+			// stay where the player was, so they can try again
+			self.try_add_loaded_player(state, &[])?;
+			return Err(err);
+		}
+		Ok(())
+	}
+	
 	pub fn update(&mut self) {
 		self.migrate();
-		for room in self.rooms.values_mut() {
-			room.update(self.time);
-		}
+		// rooms are independent of each other so they can be updated in parallel
+		let time = self.time;
+		self.rooms.par_iter_mut().for_each(|(_id, room)| room.update(time));
 		self.time.0 += 1;
 	}
 	
 	fn migrate(&mut self) {
+		let mut roomids: Vec<RoomId> = self.rooms.keys().cloned().collect();
+		roomids.sort_by(|a, b| a.0.cmp(&b.0));
 		let mut migrants = Vec::new();
-		for room in self.rooms.values_mut() {
-			migrants.append(&mut room.emigrate());
+		let mut respawns = Vec::new();
+		for roomid in roomids {
+			let mut emigration = self.rooms.get_mut(&roomid).unwrap().emigrate();
+			migrants.append(&mut emigration.emigrants);
+			respawns.append(&mut emigration.respawns);
 		}
 		for (player, destination, roompos) in migrants {
 			self.migrate_player(&player, destination, roompos).unwrap();
 		}
+		for player in respawns {
+			if let Err(err) = self.respawn_player(&player) {
+				println!("failed to respawn player {:?}: {:?}", player, err);
+			}
+		}
 	}
 	
-	pub fn save(&self) {
-		for room in self.rooms.values() {
-			if let Err(err) = self.persistence.save_room(room.id.clone(), room.save()) {
-				println!("{:?}",err);
+	/// Save the rooms where something moved, was created, was removed or changed its saved state, and the players that changed
+	pub fn save(&mut self) {
+		self.save_rooms(false);
+	}
+	
+	/// Save all loaded rooms, even when nothing changed since the last save
+	pub fn save_all(&mut self) {
+		self.save_rooms(true);
+	}
+	
+	fn save_rooms(&mut self, all: bool) {
+		for room in self.rooms.values_mut() {
+			if all || room.has_unsaved_changes() {
+				match self.persistence.save_room(room.id.clone(), room.save()) {
+					Ok(()) => room.mark_saved(),
+					Err(err) => println!("{:?}",err)
+				}
 			}
 			for (playerid, state) in room.save_players() {
-				if let Err(err) = self.persistence.save_player(playerid.clone(), state.clone()) {
-					println!("{:?}",err);
+				if self.saved_players.get(&playerid) == Some(&state) {
+					continue;
+				}
+				match self.persistence.save_player(playerid.clone(), state.clone()) {
+					Ok(()) => {self.saved_players.insert(playerid, state);}
+					Err(err) => println!("{:?}",err)
 				}
 			}
 		}
@@ -203,6 +267,13 @@ impl World {
 		}
 	}
 	
+	/// Wait until all saves are written to the persistent storage
+	pub fn flush_saves(&self) {
+		if let Err(err) = self.persistence.flush() {
+			println!("failed to flush saves: {:?}", err);
+		}
+	}
+	
 	pub fn unload_rooms(&mut self, min_age: i64){
 		let mut to_remove = Vec::new();
 		for roomid in self.rooms.keys() {
@@ -217,8 +288,51 @@ impl World {
 		}
 		for roomid in to_remove {
 			println!("unloading room '{}'", roomid);
-			self.rooms.remove(&roomid);
+			let room = self.rooms.remove(&roomid).unwrap();
+			// always save, so the saved time is the last simulated step and changes inside components are kept
+			if let Err(err) = self.persistence.save_room(roomid, room.save()) {
+				println!("{:?}", err);
+			}
+		}
+	}
+	
+	/// Load the world meta, encyclopediae and maps again and use them in all loaded rooms.
+	/// When reset_rooms is set, the rooms without players are recreated from their (new) template and their save.
+	/// Returns the number of recreated rooms.
+	pub fn reload_content(&mut self, reset_rooms: bool) -> Result<usize> {
+		let meta = self.template_loader.load_world_meta()?;
+		let encyclopedia = self.template_loader.load_encyclopediae(&meta.encyclopediae)?;
+		for id in self.rooms.keys() {
+			if *id == purgatory::purgatory_id() {
+				continue;
+			}
+			let template = self.template_loader.load_room(id.clone()).map_err(|e| aerr!("failed to load room {}: {}", id, e))?;
+			for entity in template.field.iter().flatten() {
+				encyclopedia.construct(entity).map_err(|e| aerr!("invalid entity in room {}: {}", id, e))?;
+			}
+		}
+		self.default_room = meta.default_room;
+		self.encyclopedia = encyclopedia;
+		self.clock = meta.clock;
+		self.death_penalty = meta.death_penalty;
+		for room in self.rooms.values_mut() {
+			room.set_encyclopedia(self.encyclopedia.clone());
+			room.set_clock(self.clock);
+			room.set_death_penalty(self.death_penalty);
 		}
+		if !reset_rooms {
+			return Ok(0);
+		}
+		let empty_rooms: Vec<RoomId> = self.rooms.iter()
+			.filter(|(id, room)| **id != purgatory::purgatory_id() && !room.has_players())
+			.map(|(id, _room)| id.clone())
+			.collect();
+		for id in empty_rooms.iter() {
+			let room = self.rooms.remove(id).unwrap();
+			self.persistence.save_room(id.clone(), room.save())?;
+			self.get_room_mut(id)?;
+		}
+		Ok(empty_rooms.len())
 	}
 	
 	pub fn view(&self) -> HashMap<PlayerId, WorldMessage> {
@@ -232,3 +346,130 @@ impl World {
 	}
 }
 
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::path::PathBuf;
+	use crate::{persistence::MemoryStorage, Pos};
+	
+	fn test_world(storage: &MemoryStorage) -> World {
+		let loader = WorldLoader::new(PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("content"));
+		let meta = loader.load_world_meta().unwrap();
+		let encyclopedia = loader.load_encyclopediae(&meta.encyclopediae).unwrap();
+		World::new(encyclopedia, loader, Box::new(storage.clone()), meta.default_room, meta.clock)
+	}
+	
+	#[test]
+	fn player_is_saved_when_leaving() {
+		let storage = MemoryStorage::default();
+		let mut world = test_world(&storage);
+		let player = PlayerId("bob".to_string());
+		world.add_player(&player).unwrap();
+		world.update();
+		assert!(world.view().contains_key(&player));
+		world.remove_player(&player).unwrap();
+		let state = storage.load_player(player).ok().unwrap();
+		assert_eq!(state.room, Some(world.default_room.clone()));
+	}
+	
+	#[test]
+	fn time_is_restored() {
+		let storage = MemoryStorage::default();
+		let mut world = test_world(&storage);
+		world.update();
+		world.update();
+		world.save();
+		assert_eq!(test_world(&storage).time, world.time);
+	}
+	
+	#[test]
+	fn unloaded_room_is_saved_with_last_time() {
+		let storage = MemoryStorage::default();
+		let mut world = test_world(&storage);
+		let id = RoomId("begin".to_string());
+		world.get_room_mut(&id).unwrap();
+		world.update();
+		world.save();
+		for _ in 0..5 {
+			world.update();
+		}
+		let last_time = world.rooms[&id].get_time();
+		world.unload_rooms(0);
+		assert!(world.rooms.is_empty());
+		assert_eq!(storage.load_room(id).ok().unwrap().time, Some(last_time));
+	}
+	
+	#[test]
+	fn respawn_in_purgatory_moves_player_to_respawn_point() {
+		let storage = MemoryStorage::default();
+		let mut world = test_world(&storage);
+		let player = PlayerId("bob".to_string());
+		let respawn_room = RoomId("begin".to_string());
+		let mut state = PlayerState::new(player.clone());
+		state.respawn_point = Some((respawn_room.clone(), Pos::new(16, 30)));
+		storage.save_player(player.clone(), state).unwrap();
+		world.add_player(&player).unwrap();
+		world.update();
+		world.migrate_player(&player, purgatory::purgatory_id(), RoomPos::Unknown).unwrap();
+		world.update();
+		assert_eq!(world.players[&player], purgatory::purgatory_id());
+		world.control_player(player.clone(), Control::Respawn).unwrap();
+		world.update();
+		world.update();
+		assert_eq!(world.players[&player], respawn_room);
+		world.update();
+		assert_eq!(world.view()[&player].pos, Some(Pos::new(16, 30)));
+	}
+	
+	#[test]
+	fn player_does_not_enter_blocked_tile() {
+		let storage = MemoryStorage::default();
+		let mut world = test_world(&storage);
+		let player = PlayerId("bob".to_string());
+		world.add_player(&player).unwrap();
+		world.update();
+		let start = world.players[&player].clone();
+		let destination = RoomId("begin".to_string());
+		// water
+		world.migrate_player(&player, destination.clone(), RoomPos::Pos(Pos::new(40, 0))).unwrap();
+		assert_eq!(world.players[&player], start);
+		// grass
+		world.migrate_player(&player, destination.clone(), RoomPos::Pos(Pos::new(0, 0))).unwrap();
+		assert_eq!(world.players[&player], destination);
+	}
+	
+	#[test]
+	fn content_can_be_reloaded() {
+		let storage = MemoryStorage::default();
+		let mut world = test_world(&storage);
+		let player = PlayerId("bob".to_string());
+		world.add_player(&player).unwrap();
+		world.update();
+		world.get_room_mut(&RoomId("begin".to_string())).unwrap();
+		world.update();
+		assert_eq!(world.reload_content(false).unwrap(), 0);
+		assert_eq!(world.reload_content(true).unwrap(), 1);
+		assert_eq!(world.rooms.len(), 2);
+		world.update();
+		assert!(world.view().contains_key(&player));
+	}
+	
+	#[test]
+	fn generated_rooms_load() {
+		let storage = MemoryStorage::default();
+		let mut world = test_world(&storage);
+		world.get_room_mut(&RoomId("mine+1".to_string())).unwrap();
+		world.get_room_mut(&RoomId("mine+2".to_string())).unwrap();
+		world.update();
+	}
+	
+	#[test]
+	fn all_maps_load() {
+		let storage = MemoryStorage::default();
+		let world = test_world(&storage);
+		for name in crate::util::list_names_with_suffix(world.template_loader.directory.join("maps"), ".json").unwrap() {
+			world.template_loader.load_room(RoomId(name.clone())).map_err(|e| format!("{}: {}", name, e)).unwrap();
+		}
+	}
+}
@@ -1,14 +1,18 @@
 
 use std::path::{PathBuf};
+use std::collections::HashMap;
 use std::fs;
 use json5;
 use serde::{Serialize, Deserialize};
 use crate::{
 	RoomId,
-	roomtemplate::RoomTemplate,
+	roomtemplate::{RoomTemplate, RoomTemplateSave, Stamp},
+	roomgenerator::GeneratedRoom,
 	Result,
 	aerr,
-	Encyclopedia
+	Encyclopedia,
+	clock::Clock,
+	deathpenalty::DeathPenalty
 };
 
 
@@ -34,8 +38,24 @@ impl WorldLoader {
 		let fname = id.to_string().splitn(2, '+').next().unwrap().to_string() + ".json";
 		let path = self.directory.join("maps").join(fname);
 		let text = fs::read_to_string(path)?;
-		let template = json5::from_str(&text)?;
-		Ok(template)
+		let value: serde_json::Value = json5::from_str(&text)?;
+		if value.get("generator").is_some() {
+			return Ok(GeneratedRoom::from_value(value, &id)?.generate(&id));
+		}
+		let save = RoomTemplateSave::deserialize(value)?;
+		let mut stamps = HashMap::new();
+		for placement in save.stamps.iter() {
+			if !stamps.contains_key(&placement.stamp) {
+				stamps.insert(placement.stamp.clone(), self.load_stamp(&placement.stamp)?);
+			}
+		}
+		Ok(save.build(&stamps).map_err(|e| aerr!("invalid map for room {}: {}", id, e))?)
+	}
+	
+	pub fn load_stamp(&self, name: &str) -> Result<Stamp> {
+		let path = self.directory.join("stamps").join(name.to_string() + ".json");
+		let text = fs::read_to_string(path).map_err(|e| aerr!("failed to load stamp {}: {}", name, e))?;
+		Ok(json5::from_str(&text)?)
 	}
 	
 	pub fn load_encyclopedia(&self, name: &str) -> Result<Encyclopedia> {
@@ -48,14 +68,30 @@ impl WorldLoader {
 						.join(&fname)
 				)?
 			).map_err(|e|aerr!("failed to load encyclopedia {}: {}", name, e))?;
+		encyclopedia.validate_structure()?;
+		Ok(encyclopedia)
+	}
+	
+	pub fn load_encyclopediae(&self, names: &[String]) -> Result<Encyclopedia> {
+		let mut encyclopedia = Encyclopedia::default();
+		for name in names {
+			encyclopedia = encyclopedia.merge(
+				self.load_encyclopedia(name).map_err(|e| aerr!("Failed to load encyclopedia {}: {}", name, e))?
+			);
+		}
+		// names can refer to declarations in other encyclopediae
 		encyclopedia.validate()?;
 		Ok(encyclopedia)
 	}
 }
 
-#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub struct WorldMeta {
 	pub encyclopediae: Vec<String>,
-	pub default_room: RoomId
+	pub default_room: RoomId,
+	#[serde(default)]
+	pub clock: Clock,
+	#[serde(default)]
+	pub death_penalty: DeathPenalty
 }
 
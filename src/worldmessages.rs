@@ -61,6 +61,8 @@ worldmessages!(
 	health, HealthMessage, "health", true;
 	ground, GroundMessage, "ground", true;
 	sounds, SoundMessage, "messages", false;
+	clock, ClockMessage, "time", true;
+	effects, EffectsMessage, "effects", true;
 );
 
 
@@ -69,6 +71,17 @@ pub type HealthMessage = (i64, i64);
 pub type InventoryMessage = Vec<(String, bool)>;
 pub type GroundMessage = Vec<String>;
 pub type SoundMessage = Vec<(String, String, Value)>;
+/// Active status effects: kind, amount and the remaining steps, rounded up to whole effect intervals so it does not change every step
+pub type EffectsMessage = Vec<(String, i64, i64)>;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ClockMessage {
+	pub day: i64,
+	/// fraction of the day since midnight, rounded to percents so it does not change every step
+	pub time: f64,
+	pub night: bool,
+	pub season: String
+}
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
 pub struct FieldMessage {